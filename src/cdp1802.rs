@@ -0,0 +1,307 @@
+/// RCA CDP1802 processor of the COSMAC VIP, used to run the machine code routines that hybrid
+/// ROMs call with `0nnn`.
+///
+/// Only the processor itself is modeled: interrupts, DMA (and with it the 1861 display) and the
+/// EF input lines never become active, and I/O instructions read zero from the bus.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Cdp1802 {
+    /// Scratchpad registers R0 to RF.
+    pub r: [u16; 16],
+    /// Data register, the accumulator.
+    pub d: u8,
+    /// Data flag, set on a carry and cleared on a borrow.
+    pub df: bool,
+    /// Designator of the register used as the program counter.
+    pub p: u8,
+    /// Designator of the register used as the data pointer.
+    pub x: u8,
+    /// X and P saved by `MARK`.
+    pub t: u8,
+    /// Whether interrupts are enabled.
+    pub ie: bool,
+    /// Output flip-flop, which drives the speaker of the VIP.
+    pub q: bool,
+}
+
+impl Cdp1802 {
+    /// Register the VIP interpreter keeps its program counter in, machine code routines return
+    /// to the interpreter with `SEP R4` (`D4`).
+    pub const INTERPRETER_REGISTER: u8 = 4;
+
+    /// Runs the routine at `R(P)` until it returns to the interpreter, see
+    /// [`Self::INTERPRETER_REGISTER`].
+    ///
+    /// Returns the number of executed machine cycles, or `None` if the routine did not return
+    /// within `max_cycles` machine cycles.
+    pub fn run_subroutine(&mut self, memory: &mut [u8], max_cycles: u64) -> Option<u64> {
+        let mut cycles = 0;
+        while self.p != Self::INTERPRETER_REGISTER {
+            if cycles >= max_cycles {
+                return None;
+            }
+            cycles += self.step(memory) as u64;
+        }
+        Some(cycles)
+    }
+
+    /// Executes a single instruction, returning the number of machine cycles it took.
+    pub fn step(&mut self, memory: &mut [u8]) -> u32 {
+        let opcode = self.fetch(memory);
+        let n = opcode & 0xF;
+        let (rn, rx) = (n as usize, self.x as usize);
+        match opcode >> 4 {
+            // IDL waits for an interrupt or DMA, neither of which ever happens
+            0x0 if n == 0 => {}
+            0x0 => self.d = read(memory, self.r[rn]),
+            0x1 => self.r[rn] = self.r[rn].wrapping_add(1),
+            0x2 => self.r[rn] = self.r[rn].wrapping_sub(1),
+            0x3 => {
+                let target = self.fetch(memory);
+                if self.condition(n) {
+                    let pc = &mut self.r[self.p as usize];
+                    *pc = (*pc & 0xFF00) | target as u16;
+                }
+            }
+            0x4 => {
+                self.d = read(memory, self.r[rn]);
+                self.r[rn] = self.r[rn].wrapping_add(1);
+            }
+            0x5 => write(memory, self.r[rn], self.d),
+            // IRX, and OUT, which puts M(R(X)) on the bus, where nothing listens
+            0x6 if n < 8 => self.r[rx] = self.r[rx].wrapping_add(1),
+            // INP reads the bus, which is never driven
+            0x6 if n > 8 => {
+                write(memory, self.r[rx], 0);
+                self.d = 0;
+            }
+            0x6 => {}
+            0x7 => self.execute_control(memory, n),
+            0x8 => self.d = self.r[rn] as u8,
+            0x9 => self.d = (self.r[rn] >> 8) as u8,
+            0xA => self.r[rn] = (self.r[rn] & 0xFF00) | self.d as u16,
+            0xB => self.r[rn] = (self.r[rn] & 0x00FF) | ((self.d as u16) << 8),
+            0xC => {
+                self.execute_long(memory, n);
+                return 3;
+            }
+            0xD => self.p = n,
+            0xE => self.x = n,
+            _ => self.execute_alu(memory, n),
+        }
+        2
+    }
+
+    /// Reads the byte at `R(P)` and advances it.
+    fn fetch(&mut self, memory: &[u8]) -> u8 {
+        let pc = &mut self.r[self.p as usize];
+        let byte = read(memory, *pc);
+        *pc = pc.wrapping_add(1);
+        byte
+    }
+
+    /// Condition of the short branch `3n`, or of the long branch `Cn`.
+    fn condition(&self, n: u8) -> bool {
+        let condition = match n & 0x7 {
+            0 => true,
+            1 => self.q,
+            2 => self.d == 0,
+            3 => self.df,
+            // EF1 to EF4 are never active
+            _ => false,
+        };
+        // the upper half of the opcodes negates the condition, e.g. BNZ or SKP
+        condition != (n & 0x8 != 0)
+    }
+
+    /// Executes the `7n` control, carry arithmetic and Q instructions.
+    fn execute_control(&mut self, memory: &mut [u8], n: u8) {
+        let rx = self.x as usize;
+        match n {
+            // RET and DIS
+            0x0 | 0x1 => {
+                let xp = read(memory, self.r[rx]);
+                self.r[rx] = self.r[rx].wrapping_add(1);
+                (self.x, self.p) = (xp >> 4, xp & 0xF);
+                self.ie = n == 0x0;
+            }
+            // LDXA
+            0x2 => {
+                self.d = read(memory, self.r[rx]);
+                self.r[rx] = self.r[rx].wrapping_add(1);
+            }
+            // STXD
+            0x3 => {
+                write(memory, self.r[rx], self.d);
+                self.r[rx] = self.r[rx].wrapping_sub(1);
+            }
+            // SAV
+            0x8 => write(memory, self.r[rx], self.t),
+            // MARK
+            0x9 => {
+                self.t = (self.x << 4) | self.p;
+                write(memory, self.r[2], self.t);
+                self.x = self.p;
+                self.r[2] = self.r[2].wrapping_sub(1);
+            }
+            0xA => self.q = false,
+            0xB => self.q = true,
+            // ADC, SDB, SHRC, SMB and their immediate versions
+            _ => {
+                let operand = if n & 0x8 != 0 {
+                    self.fetch(memory)
+                } else {
+                    read(memory, self.r[rx])
+                };
+                match n & 0x3 {
+                    0x0 => self.add(operand, self.df),
+                    0x1 => self.subtract(operand, self.d, self.df),
+                    0x2 if n & 0x8 != 0 => self.shift_left(self.df),
+                    0x2 => self.shift_right(self.df),
+                    _ => self.subtract(self.d, operand, self.df),
+                }
+            }
+        }
+    }
+
+    /// Executes the `Cn` long branches and skips.
+    fn execute_long(&mut self, memory: &[u8], n: u8) {
+        let pc = self.p as usize;
+        let taken = match n {
+            // NOP and LSKP
+            0x4 => false,
+            0x8 => true,
+            // LSIE
+            0xC => self.ie,
+            // the long skips test the inverse of the long branch four opcodes below, e.g. LSZ
+            // skips if LBNZ would not branch
+            0x5..=0x7 | 0xD..=0xF => !self.condition(n - 4),
+            _ => self.condition(n),
+        };
+        if matches!(n, 0x4..=0x8 | 0xC..=0xF) {
+            if taken {
+                self.r[pc] = self.r[pc].wrapping_add(2);
+            }
+        } else if taken {
+            let high = read(memory, self.r[pc]);
+            let low = read(memory, self.r[pc].wrapping_add(1));
+            self.r[pc] = u16::from_be_bytes([high, low]);
+        } else {
+            self.r[pc] = self.r[pc].wrapping_add(2);
+        }
+    }
+
+    /// Executes the `Fn` logic and arithmetic instructions.
+    fn execute_alu(&mut self, memory: &[u8], n: u8) {
+        let operand = match n {
+            // the immediate versions read the byte after the opcode
+            0x8..=0xD | 0xF => self.fetch(memory),
+            _ => read(memory, self.r[self.x as usize]),
+        };
+        match n {
+            0x0 | 0x8 => self.d = operand,
+            0x1 | 0x9 => self.d |= operand,
+            0x2 | 0xA => self.d &= operand,
+            0x3 | 0xB => self.d ^= operand,
+            0x4 | 0xC => self.add(operand, false),
+            0x5 | 0xD => self.subtract(operand, self.d, true),
+            0x6 => self.shift_right(false),
+            0x7 | 0xF => self.subtract(self.d, operand, true),
+            _ => self.shift_left(false),
+        }
+    }
+
+    /// Sets D to D + `operand` + `carry`, DF to the carry out.
+    fn add(&mut self, operand: u8, carry: bool) {
+        let sum = self.d as u16 + operand as u16 + carry as u16;
+        self.d = sum as u8;
+        self.df = sum > 0xFF;
+    }
+
+    /// Sets D to `minuend` - `subtrahend`, minus one unless `no_borrow`; DF is cleared on a
+    /// borrow.
+    fn subtract(&mut self, minuend: u8, subtrahend: u8, no_borrow: bool) {
+        let difference = minuend as i16 - subtrahend as i16 - !no_borrow as i16;
+        self.d = difference as u8;
+        self.df = difference >= 0;
+    }
+
+    /// Shifts D right, shifting in `carry` and the lowest bit out into DF.
+    fn shift_right(&mut self, carry: bool) {
+        let bit = self.d & 1 != 0;
+        self.d = (self.d >> 1) | ((carry as u8) << 7);
+        self.df = bit;
+    }
+
+    /// Shifts D left, shifting in `carry` and the highest bit out into DF.
+    fn shift_left(&mut self, carry: bool) {
+        let bit = self.d & 0x80 != 0;
+        self.d = (self.d << 1) | carry as u8;
+        self.df = bit;
+    }
+}
+
+/// Reads the byte at `address`, which wraps around the memory like on the VIP.
+fn read(memory: &[u8], address: u16) -> u8 {
+    memory[address as usize % memory.len()]
+}
+
+/// Writes the byte at `address`, which wraps around the memory like on the VIP.
+fn write(memory: &mut [u8], address: u16, byte: u8) {
+    let len = memory.len();
+    memory[address as usize % len] = byte;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the routine `code` at address 0 on a 4 KB memory.
+    fn run(code: &[u8]) -> (Cdp1802, Vec<u8>) {
+        let mut memory = vec![0; 0x1000];
+        memory[..code.len()].copy_from_slice(code);
+        let mut cpu = Cdp1802::default();
+        assert!(cpu.run_subroutine(&mut memory, 1000).is_some());
+        (cpu, memory)
+    }
+
+    #[test]
+    fn loop_counts_down_register() {
+        // LDI 0x05; PLO R1; loop: DEC R1; GLO R1; BNZ loop; SEP R4
+        let (cpu, _) = run(&[0xF8, 0x05, 0xA1, 0x21, 0x81, 0x3A, 0x03, 0xD4]);
+        assert_eq!(cpu.r[1], 0);
+        assert_eq!(cpu.d, 0);
+        assert_eq!(cpu.p, Cdp1802::INTERPRETER_REGISTER);
+    }
+
+    #[test]
+    fn arithmetic_sets_data_flag() {
+        // LDI 0x08; PHI R2; LDI 0xF0; STR R2; SEX R2; LDI 0x20; ADD; PLO R3; SM; SEP R4
+        let (cpu, memory) = run(&[
+            0xF8, 0x08, 0xB2, 0xF8, 0xF0, 0x52, 0xE2, 0xF8, 0x20, 0xF4, 0xA3, 0xF7, 0xD4,
+        ]);
+        assert_eq!(memory[0x800], 0xF0);
+        // 0x20 + 0xF0 carries
+        assert_eq!(cpu.r[3], 0x10);
+        // 0x10 - 0xF0 borrows
+        assert_eq!(cpu.d, 0x20);
+        assert!(!cpu.df);
+    }
+
+    #[test]
+    fn long_branch_and_q() {
+        // SEQ; LBR 0x0010; ...; 0x10: LSQ; SEP R4; REQ; SEP R4
+        let mut code = vec![0x7B, 0xC0, 0x00, 0x10];
+        code.resize(0x10, 0x00);
+        code.extend([0xCD, 0xD4, 0x00, 0x7A, 0xD4]);
+        let (cpu, _) = run(&code);
+        assert!(!cpu.q);
+    }
+
+    #[test]
+    fn endless_routine_is_stopped() {
+        // BR 0x00
+        let mut memory = vec![0x30, 0x00];
+        let mut cpu = Cdp1802::default();
+        assert_eq!(cpu.run_subroutine(&mut memory, 100), None);
+    }
+}
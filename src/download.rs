@@ -0,0 +1,110 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use chip8_emulator::interpreter::RAM_SIZE;
+
+/// Time after which connecting to or reading from the server is aborted.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Downloads the ROM at the given `http://` url into memory.
+///
+/// Only plain HTTP is supported, to avoid pulling in a TLS implementation.
+pub fn download_rom(url: &str) -> Result<Vec<u8>, String> {
+    let location = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("Unsupported URL {url}, only http:// is supported"))?;
+    let (host, path) = match location.find('/') {
+        Some(idx) => location.split_at(idx),
+        None => (location, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+
+    let mut stream = TcpStream::connect(&address)
+        .map_err(|err| format!("Failed to connect to {host}: {err}"))?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+        .map_err(|err| err.to_string())?;
+    // HTTP/1.0 avoids chunked responses and closes the connection after the body
+    write!(
+        stream,
+        "GET {path} HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\n\r\n"
+    )
+    .map_err(|err| format!("Failed to send request: {err}"))?;
+    read_response(stream)
+}
+
+/// Reads an HTTP response from `reader` and returns its body.
+///
+/// Fails if the status is not `200` or the body does not fit into the memory of the interpreter.
+fn read_response(reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut reader = BufReader::new(reader);
+    let mut status = String::new();
+    reader
+        .read_line(&mut status)
+        .map_err(|err| format!("Failed to read response: {err}"))?;
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("Unexpected response: {}", status.trim_end()));
+    }
+    // skip the headers, which are terminated by an empty line
+    loop {
+        let mut header = String::new();
+        let read = reader
+            .read_line(&mut header)
+            .map_err(|err| format!("Failed to read response: {err}"))?;
+        if read == 0 || header.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut rom_file = Vec::new();
+    // read at most one byte more than the whole memory, so oversized ROMs are detected without
+    // downloading them completely; whether the ROM fits after the start address is checked when
+    // it is loaded
+    reader
+        .take(RAM_SIZE as u64 + 1)
+        .read_to_end(&mut rom_file)
+        .map_err(|err| format!("Failed to read response: {err}"))?;
+    if rom_file.is_empty() {
+        return Err("ROM is empty".to_string());
+    }
+    if rom_file.len() > RAM_SIZE {
+        return Err(format!(
+            "ROM is too large, at most {RAM_SIZE} bytes fit into the memory"
+        ));
+    }
+    Ok(rom_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_body_is_returned() {
+        let response: &[u8] =
+            b"HTTP/1.0 200 OK\r\nContent-Type: application/octet-stream\r\n\r\n\x00\xE0\x12\x02";
+        assert_eq!(read_response(response), Ok(vec![0x00, 0xE0, 0x12, 0x02]));
+    }
+
+    #[test]
+    fn invalid_responses_are_rejected() {
+        let not_found: &[u8] = b"HTTP/1.0 404 Not Found\r\n\r\n";
+        assert_eq!(
+            read_response(not_found),
+            Err("Unexpected response: HTTP/1.0 404 Not Found".to_string())
+        );
+        let empty: &[u8] = b"HTTP/1.0 200 OK\r\n\r\n";
+        assert!(read_response(empty).is_err());
+        let mut too_large = b"HTTP/1.0 200 OK\r\n\r\n".to_vec();
+        too_large.resize(too_large.len() + RAM_SIZE + 1, 0xFF);
+        assert!(read_response(too_large.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use std::{error::Error, fmt::Display};
+
+use crate::instruction::Instruction;
+
+/// Errors which stop the interpreter or prevent a ROM from being loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// The opcode is not part of the instruction set.
+    InvalidOpcode {
+        opcode: u16,
+        /// Opcodes which would have been valid, if only the low nibble is undefined.
+        expected: Option<&'static str>,
+    },
+    /// The instruction family is not allowed, see [`crate::Interpreter::set_allowed_mnemonics`].
+    DisallowedInstruction {
+        opcode: u16,
+        mnemonic: &'static str,
+        address: u16,
+    },
+    /// A return at `address` without a matching subroutine call.
+    StackUnderflow { address: u16 },
+    /// A call at `address`, while the stack is already full.
+    StackOverflow { address: u16 },
+    /// The jump or call at `address` targets an odd address, while alignment is enforced.
+    UnalignedJump {
+        instruction: Instruction,
+        address: u16,
+        target: u16,
+    },
+    /// The jump or call at `address` targets the memory reserved for the interpreter, while
+    /// decoding strictly.
+    ReservedJump {
+        instruction: Instruction,
+        address: u16,
+        target: u16,
+    },
+    /// `len` bytes starting at `address` do not fit into the memory.
+    OutOfBounds { address: usize, len: usize },
+    /// The ROM does not fit into the memory after its start address.
+    RomTooLarge { size: usize, available: usize },
+    /// The ROM does not contain any bytes.
+    EmptyRom,
+    /// A ROM given as hex bytes contains a character which is not a hex digit.
+    InvalidHexDigit(char),
+    /// A ROM given as hex bytes contains an odd number of digits.
+    OddHexDigitCount(usize),
+    /// The machine code routine at `address` did not return to the interpreter, see
+    /// [`crate::Interpreter::set_vip_native`].
+    NativeRoutineStuck { address: u16 },
+    /// A custom extension failed to handle the `opcode`.
+    Extension { opcode: u16, message: String },
+    /// The window thread panicked with the given message and is no longer running.
+    WindowCrashed(String),
+}
+
+impl Display for Chip8Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidOpcode {
+                opcode,
+                expected: Some(expected),
+            } => write!(f, "Invalid opcode {opcode:04X}, expected {expected}"),
+            Self::InvalidOpcode {
+                opcode,
+                expected: None,
+            } => write!(f, "Invalid opcode {opcode:04X}"),
+            Self::DisallowedInstruction {
+                opcode,
+                mnemonic,
+                address,
+            } => write!(
+                f,
+                "Instruction {opcode:04X} ({mnemonic}) at {address:#05X} is not allowed"
+            ),
+            Self::StackUnderflow { address } => write!(
+                f,
+                "Stack underflow: return at {address:#05X} without a subroutine call"
+            ),
+            Self::StackOverflow { address } => write!(
+                f,
+                "Stack overflow: call at {address:#05X} exceeds the maximum nesting depth"
+            ),
+            Self::UnalignedJump {
+                instruction,
+                address,
+                target,
+            } => write!(
+                f,
+                "{instruction:?} at {address:#05X} jumps to unaligned address {target:#05X}"
+            ),
+            Self::ReservedJump {
+                instruction,
+                address,
+                target,
+            } => write!(
+                f,
+                "{instruction:?} at {address:#05X} jumps to reserved memory at {target:#05X}"
+            ),
+            Self::OutOfBounds { address, len } => write!(
+                f,
+                "{len} bytes at {address:#05X} exceed the memory of the interpreter"
+            ),
+            Self::RomTooLarge { size, available } => write!(
+                f,
+                "ROM is too large ({size} bytes, at most {available} bytes are available)"
+            ),
+            Self::EmptyRom => write!(f, "ROM is empty"),
+            Self::InvalidHexDigit(c) => write!(f, "Invalid hex digit '{c}'"),
+            Self::OddHexDigitCount(count) => write!(f, "Odd number of hex digits ({count})"),
+            Self::NativeRoutineStuck { address } => write!(
+                f,
+                "Machine code routine at {address:#05X} did not return to the interpreter"
+            ),
+            Self::Extension { opcode, message } => {
+                write!(
+                    f,
+                    "Extension failed to handle opcode {opcode:04X}: {message}"
+                )
+            }
+            Self::WindowCrashed(message) => write!(f, "Window crashed: {message}"),
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_include_details() {
+        assert_eq!(
+            Chip8Error::InvalidOpcode {
+                opcode: 0x5121,
+                expected: Some("5xy0"),
+            }
+            .to_string(),
+            "Invalid opcode 5121, expected 5xy0"
+        );
+        assert_eq!(
+            Chip8Error::StackUnderflow { address: 0x2AE }.to_string(),
+            "Stack underflow: return at 0x2AE without a subroutine call"
+        );
+        assert_eq!(
+            Chip8Error::RomTooLarge {
+                size: 4000,
+                available: 3584
+            }
+            .to_string(),
+            "ROM is too large (4000 bytes, at most 3584 bytes are available)"
+        );
+    }
+
+    #[test]
+    fn can_be_boxed_as_error() {
+        let err: Box<dyn Error> = Box::new(Chip8Error::EmptyRom);
+        assert_eq!(err.to_string(), "ROM is empty");
+    }
+}
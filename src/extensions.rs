@@ -0,0 +1,187 @@
+use std::fmt::Display;
+
+/// Extension families of the CHIP-8 instruction set, which a ROM may use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionSet {
+    /// SUPER-CHIP scroll instructions (`00Cn`, `00FB`, `00FC`).
+    pub schip_scroll: bool,
+    /// SUPER-CHIP resolution switching and exit (`00FD`, `00FE`, `00FF`).
+    pub schip_hires: bool,
+    /// SUPER-CHIP 16x16 sprites and large font (`Dxy0`, `Fx30`).
+    pub schip_large_sprites: bool,
+    /// SUPER-CHIP RPL user flags (`Fx75`, `Fx85`).
+    pub schip_flags: bool,
+    /// XO-CHIP scroll up (`00Dn`).
+    pub xo_chip_scroll: bool,
+    /// XO-CHIP register range load/store (`5xy2`, `5xy3`).
+    pub xo_chip_registers: bool,
+    /// XO-CHIP long index load (`F000 nnnn`).
+    pub xo_chip_long_load: bool,
+    /// XO-CHIP bitplane selection (`Fn01`).
+    pub xo_chip_planes: bool,
+    /// XO-CHIP audio pattern and pitch (`F002`, `Fx3A`).
+    pub xo_chip_audio: bool,
+    /// MegaChip mode switching (`0010`, `0011`).
+    pub megachip: bool,
+}
+
+impl ExtensionSet {
+    /// Checks if any SUPER-CHIP instructions are used.
+    pub fn schip(&self) -> bool {
+        self.schip_scroll || self.schip_hires || self.schip_large_sprites || self.schip_flags
+    }
+
+    /// Checks if any XO-CHIP instructions are used.
+    pub fn xo_chip(&self) -> bool {
+        self.xo_chip_scroll
+            || self.xo_chip_registers
+            || self.xo_chip_long_load
+            || self.xo_chip_planes
+            || self.xo_chip_audio
+    }
+
+    /// Checks if no extension instructions are used.
+    pub fn is_empty(&self) -> bool {
+        !self.schip() && !self.xo_chip() && !self.megachip
+    }
+
+    /// Used extension families, which are not implemented by the interpreter.
+    ///
+    /// The SUPER-CHIP instructions are supported (unless disabled, see
+    /// [`crate::interpreter::Interpreter::set_super_chip_instructions`]), the XO-CHIP and
+    /// MegaChip instructions are not.
+    pub fn unsupported(&self) -> Self {
+        Self {
+            schip_scroll: false,
+            schip_hires: false,
+            schip_large_sprites: false,
+            schip_flags: false,
+            ..*self
+        }
+    }
+
+    /// Names of the used extension families.
+    fn names(&self) -> Vec<&'static str> {
+        [
+            (self.schip_scroll, "SCHIP scroll"),
+            (self.schip_hires, "SCHIP hires"),
+            (self.schip_large_sprites, "SCHIP large sprites"),
+            (self.schip_flags, "SCHIP flags"),
+            (self.xo_chip_scroll, "XO-CHIP scroll"),
+            (self.xo_chip_registers, "XO-CHIP registers"),
+            (self.xo_chip_long_load, "XO-CHIP long load"),
+            (self.xo_chip_planes, "XO-CHIP planes"),
+            (self.xo_chip_audio, "XO-CHIP audio"),
+            (self.megachip, "MegaChip"),
+        ]
+        .into_iter()
+        .filter_map(|(used, name)| used.then_some(name))
+        .collect()
+    }
+}
+
+impl Display for ExtensionSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.names().join(", "))
+    }
+}
+
+/// Scans the `rom` for opcodes of the SUPER-CHIP, XO-CHIP and MegaChip extensions.
+///
+/// Every 2-byte aligned word is treated as an instruction, so data embedded in the ROM
+/// may cause false positives. `Dxy0` is also a valid (if useless) CHIP-8 instruction, so it
+/// only counts as a 16x16 sprite if the ROM uses other SUPER-CHIP instructions as well.
+pub fn rom_uses_extensions(rom: &[u8]) -> ExtensionSet {
+    let mut extensions = ExtensionSet::default();
+    let mut large_sprite_draw = false;
+    for word in rom.chunks_exact(2) {
+        let ident = (word[0] >> 4, word[0] & 0xF, word[1] >> 4, word[1] & 0xF);
+        match ident {
+            (0x0, 0x0, 0xC, _) | (0x0, 0x0, 0xF, 0xB) | (0x0, 0x0, 0xF, 0xC) => {
+                extensions.schip_scroll = true
+            }
+            (0x0, 0x0, 0xF, 0xD) | (0x0, 0x0, 0xF, 0xE) | (0x0, 0x0, 0xF, 0xF) => {
+                extensions.schip_hires = true
+            }
+            (0xD, _, _, 0x0) => large_sprite_draw = true,
+            (0xF, _, 0x3, 0x0) => extensions.schip_large_sprites = true,
+            (0xF, _, 0x7, 0x5) | (0xF, _, 0x8, 0x5) => extensions.schip_flags = true,
+            (0x0, 0x0, 0xD, _) => extensions.xo_chip_scroll = true,
+            (0x5, _, _, 0x2) | (0x5, _, _, 0x3) => extensions.xo_chip_registers = true,
+            (0xF, 0x0, 0x0, 0x0) => extensions.xo_chip_long_load = true,
+            (0xF, _, 0x0, 0x1) => extensions.xo_chip_planes = true,
+            (0xF, 0x0, 0x0, 0x2) | (0xF, _, 0x3, 0xA) => extensions.xo_chip_audio = true,
+            // the other MegaChip opcodes are indistinguishable from SYS calls
+            (0x0, 0x0, 0x1, 0x0) | (0x0, 0x0, 0x1, 0x1) => extensions.megachip = true,
+            _ => {}
+        }
+    }
+    extensions.schip_large_sprites |= large_sprite_draw && extensions.schip();
+    extensions
+}
+
+/// Pattern matching the opcodes handled by a custom extension.
+///
+/// An opcode matches if `opcode & mask == value`, e.g. a mask of `0xF0FF` and a value of
+/// `0xF0F0` matches all `FxF0` opcodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodePattern {
+    pub mask: u16,
+    pub value: u16,
+}
+
+impl OpcodePattern {
+    /// Checks if the `opcode` matches the pattern.
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+}
+
+/// Interpreter state which can be modified by a custom extension.
+pub struct ExtensionContext<'a> {
+    /// General purpose registers V0 to VF.
+    pub registers: &'a mut [u8; 16],
+    /// Memory of the interpreter.
+    pub memory: &'a mut [u8],
+    /// Address register I.
+    pub address_register: &'a mut u16,
+    /// Program counter, already pointing to the next instruction.
+    pub program_counter: &'a mut u16,
+}
+
+/// Handler of a custom extension, which is called with the raw opcode.
+pub type ExtensionHandler = Box<dyn FnMut(u16, ExtensionContext) -> Result<(), String>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lone_dxy0_is_not_reported() {
+        // DRW V0, V1, 0
+        assert!(rom_uses_extensions(&[0xD0, 0x10]).is_empty());
+        // HIGH; DRW V0, V1, 0
+        let extensions = rom_uses_extensions(&[0x00, 0xFF, 0xD0, 0x10]);
+        assert!(extensions.schip_hires);
+        assert!(extensions.schip_large_sprites);
+    }
+
+    #[test]
+    fn scroll_opcode_is_reported_as_schip() {
+        // CLS; SCD 4
+        let extensions = rom_uses_extensions(&[0x00, 0xE0, 0x00, 0xC4]);
+        assert!(extensions.schip_scroll);
+        assert!(!extensions.xo_chip());
+        assert_eq!(extensions.to_string(), "SCHIP scroll");
+        assert!(rom_uses_extensions(&[0x00, 0xE0, 0x12, 0x00]).is_empty());
+    }
+
+    #[test]
+    fn megachip_mode_switch_is_reported() {
+        // MEGAON; CLS
+        let extensions = rom_uses_extensions(&[0x00, 0x11, 0x00, 0xE0]);
+        assert!(extensions.megachip);
+        assert!(!extensions.schip() && !extensions.xo_chip());
+        assert_eq!(extensions.unsupported().to_string(), "MegaChip");
+    }
+}
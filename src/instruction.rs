@@ -1,4 +1,6 @@
-#[derive(Debug)]
+use crate::{error::Chip8Error, quirks::Quirks};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     /// Jump to a machine code routine at `addr`.
     ///
@@ -138,12 +140,46 @@ pub enum Instruction {
     ///
     /// The interpreter reads values from memory starting at location I into registers `V0` through `Vx`.
     LdVxI(u8),
+    /// Scroll the display down by `n` pixels (SUPER-CHIP).
+    Scd(u8),
+    /// Scroll the display right by 4 pixels (SUPER-CHIP).
+    Scr,
+    /// Scroll the display left by 4 pixels (SUPER-CHIP).
+    Scl,
+    /// Exit the interpreter (SUPER-CHIP).
+    Exit,
+    /// Switch to the 64x32 low resolution mode (SUPER-CHIP).
+    Low,
+    /// Switch to the 128x64 high resolution mode (SUPER-CHIP).
+    High,
+    /// Set I = location of the 8x10 sprite for digit `Vx` (SUPER-CHIP).
+    LdHfVx(u8),
+    /// Store registers `V0` through `Vx` in the RPL user flags, `x` < 8 (SUPER-CHIP).
+    LdRVx(u8),
+    /// Read registers `V0` through `Vx` from the RPL user flags, `x` < 8 (SUPER-CHIP).
+    LdVxR(u8),
+    /// Reserved opcode, which does nothing.
+    ///
+    /// Only produced when decoding leniently, see [`Instruction::decode`].
+    Nop,
+    /// Opcode handled by a custom extension, see [`crate::interpreter::Interpreter::register_extension`].
+    Extension(u16),
 }
 
 impl TryFrom<u16> for Instruction {
-    type Error = String;
+    type Error = Chip8Error;
 
     fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::decode(value, false)
+    }
+}
+
+impl Instruction {
+    /// Decodes the given opcode.
+    ///
+    /// Opcodes with an undefined low nibble (e.g. `8xy9` or `00E1`) are rejected if `strict` is set,
+    /// otherwise they are decoded as [`Instruction::Nop`].
+    pub fn decode(value: u16, strict: bool) -> Result<Self, Chip8Error> {
         let ident = (
             ((value >> 12) & 0xF),
             ((value >> 8) & 0xF),
@@ -153,9 +189,22 @@ impl TryFrom<u16> for Instruction {
 
         let address = |x, y, k| (x << 8) | (y << 4) | k;
         let byte = |y, k| ((y << 4) | k) as u8;
+        let invalid = |expected| Chip8Error::InvalidOpcode {
+            opcode: value,
+            expected,
+        };
         Ok(match ident {
+            (0x0, 0x0, 0xC, n) => Self::Scd(n as u8),
             (0x0, 0x0, 0xE, 0x0) => Self::Cls,
             (0x0, 0x0, 0xE, 0xE) => Self::Ret,
+            // not a SYS call to 0x0Ex, which would be part of the interpreter itself
+            (0x0, 0x0, 0xE, _) if !strict => Self::Nop,
+            (0x0, 0x0, 0xE, _) => return Err(invalid(Some("00E0 or 00EE"))),
+            (0x0, 0x0, 0xF, 0xB) => Self::Scr,
+            (0x0, 0x0, 0xF, 0xC) => Self::Scl,
+            (0x0, 0x0, 0xF, 0xD) => Self::Exit,
+            (0x0, 0x0, 0xF, 0xE) => Self::Low,
+            (0x0, 0x0, 0xF, 0xF) => Self::High,
             (0x0, x, y, k) => Self::Sys(address(x, y, k)),
             (0x1, x, y, k) => Self::JpAddr(address(x, y, k)),
             (0x2, x, y, k) => Self::Call(address(x, y, k)),
@@ -186,10 +235,237 @@ impl TryFrom<u16> for Instruction {
             (0xF, x, 0x1, 0x8) => Self::LdStVx(x as u8),
             (0xF, x, 0x1, 0xE) => Self::AddIVx(x as u8),
             (0xF, x, 0x2, 0x9) => Self::LdFVx(x as u8),
+            (0xF, x, 0x3, 0x0) => Self::LdHfVx(x as u8),
             (0xF, x, 0x3, 0x3) => Self::LdBVx(x as u8),
             (0xF, x, 0x5, 0x5) => Self::LdIVx(x as u8),
             (0xF, x, 0x6, 0x5) => Self::LdVxI(x as u8),
-            _ => return Err(format!("Failed to parse instruction {value:02X}")),
+            (0xF, x, 0x7, 0x5) => Self::LdRVx(x as u8),
+            (0xF, x, 0x8, 0x5) => Self::LdVxR(x as u8),
+            (0x5, ..) | (0x8, ..) | (0x9, ..) if !strict => Self::Nop,
+            (0x5, ..) => return Err(invalid(Some("5xy0"))),
+            (0x8, ..) => return Err(invalid(Some("8xy0 to 8xy7 or 8xyE"))),
+            (0x9, ..) => return Err(invalid(Some("9xy0"))),
+            _ => return Err(invalid(None)),
         })
     }
 }
+
+impl Instruction {
+    /// Mnemonics of all instruction families, see [`Instruction::mnemonic`].
+    pub const MNEMONICS: [&'static str; 28] = [
+        "sys", "cls", "ret", "jp", "call", "se", "sne", "ld", "add", "or", "and", "xor", "sub",
+        "shr", "subn", "shl", "rnd", "drw", "skp", "sknp", "scd", "scr", "scl", "exit", "low",
+        "high", "nop", "ext",
+    ];
+
+    /// Mnemonic of the instruction family, following Cowgod's reference (e.g. `ld` for all loads).
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Sys(_) => "sys",
+            Self::Cls => "cls",
+            Self::Ret => "ret",
+            Self::JpAddr(_) | Self::JpV0Addr(_) => "jp",
+            Self::Call(_) => "call",
+            Self::SeVxByte(..) | Self::SeVxVy(..) => "se",
+            Self::SneVxByte(..) | Self::SneVxVy(..) => "sne",
+            Self::LdVxByte(..)
+            | Self::LdVxVy(..)
+            | Self::LdIAddr(_)
+            | Self::LdVxDt(_)
+            | Self::LdVxK(_)
+            | Self::LdDtVx(_)
+            | Self::LdStVx(_)
+            | Self::LdFVx(_)
+            | Self::LdBVx(_)
+            | Self::LdIVx(_)
+            | Self::LdVxI(_)
+            | Self::LdHfVx(_)
+            | Self::LdRVx(_)
+            | Self::LdVxR(_) => "ld",
+            Self::AddVxByte(..) | Self::AddVxVy(..) | Self::AddIVx(_) => "add",
+            Self::Or(..) => "or",
+            Self::And(..) => "and",
+            Self::Xor(..) => "xor",
+            Self::Sub(..) => "sub",
+            Self::Shr(..) => "shr",
+            Self::Subn(..) => "subn",
+            Self::Shl(..) => "shl",
+            Self::Rnd(..) => "rnd",
+            Self::Drw(..) => "drw",
+            Self::Skp(_) => "skp",
+            Self::Sknp(_) => "sknp",
+            Self::Scd(_) => "scd",
+            Self::Scr => "scr",
+            Self::Scl => "scl",
+            Self::Exit => "exit",
+            Self::Low => "low",
+            Self::High => "high",
+            Self::Nop => "nop",
+            Self::Extension(_) => "ext",
+        }
+    }
+
+    /// Checks if the instruction is part of the SUPER-CHIP extension.
+    pub fn is_super_chip(&self) -> bool {
+        matches!(
+            self,
+            Self::Scd(_)
+                | Self::Scr
+                | Self::Scl
+                | Self::Exit
+                | Self::Low
+                | Self::High
+                | Self::LdHfVx(_)
+        )
+    }
+
+    /// Approximate number of machine cycles the instruction takes on the COSMAC VIP.
+    ///
+    /// One machine cycle of the VIP's CDP1802 takes 8 clock cycles at 1.76 MHz, ~4.54µs.
+    /// The values are based on measurements of the original interpreter, instructions whose
+    /// duration depends on their operands are approximated.
+    pub fn machine_cycles(&self) -> u32 {
+        match *self {
+            Self::Sys(_) | Self::Ret | Self::JpAddr(_) | Self::Call(_) | Self::JpV0Addr(_) => 23,
+            Self::Cls => 24,
+            Self::SeVxByte(..) | Self::SneVxByte(..) | Self::LdIAddr(_) => 12,
+            Self::SeVxVy(..) | Self::SneVxVy(..) | Self::Skp(_) | Self::Sknp(_) => 16,
+            Self::LdVxByte(..) => 6,
+            Self::AddVxByte(..) | Self::LdVxDt(_) | Self::LdDtVx(_) | Self::LdStVx(_) => 10,
+            Self::LdVxVy(..)
+            | Self::Or(..)
+            | Self::And(..)
+            | Self::Xor(..)
+            | Self::AddVxVy(..)
+            | Self::Sub(..)
+            | Self::Shr(..)
+            | Self::Subn(..)
+            | Self::Shl(..) => 44,
+            Self::Rnd(..) => 36,
+            // each sprite row is shifted into place and XORed with the display memory
+            Self::Drw(_, _, n) => 68 + 46 * n as u32,
+            Self::LdVxK(_) => 10,
+            Self::AddIVx(_) => 19,
+            Self::LdFVx(_) | Self::LdHfVx(_) => 20,
+            Self::LdBVx(_) => 204,
+            Self::LdIVx(x) | Self::LdVxI(x) => 14 + 14 * (x as u32 + 1),
+            // not part of the VIP's instruction set
+            Self::Scd(_) | Self::Scr | Self::Scl | Self::Exit | Self::Low | Self::High => 0,
+            Self::LdRVx(_) | Self::LdVxR(_) => 0,
+            Self::Nop | Self::Extension(_) => 0,
+        }
+    }
+
+    /// Bitmask of the general purpose registers read by this instruction.
+    ///
+    /// Bit `n` is set if register `Vn` is used as a source operand, which depends on the `quirks`.
+    pub fn registers_read(&self, quirks: &Quirks) -> u16 {
+        match *self {
+            Self::SeVxByte(x, _)
+            | Self::SneVxByte(x, _)
+            | Self::AddVxByte(x, _)
+            | Self::Skp(x)
+            | Self::Sknp(x)
+            | Self::LdDtVx(x)
+            | Self::LdStVx(x)
+            | Self::AddIVx(x)
+            | Self::LdFVx(x)
+            | Self::LdHfVx(x)
+            | Self::LdBVx(x) => register_mask(x),
+            Self::SeVxVy(x, y)
+            | Self::SneVxVy(x, y)
+            | Self::Or(x, y)
+            | Self::And(x, y)
+            | Self::Xor(x, y)
+            | Self::AddVxVy(x, y)
+            | Self::Sub(x, y)
+            | Self::Subn(x, y)
+            | Self::Drw(x, y, _) => register_mask(x) | register_mask(y),
+            Self::LdVxVy(_, y) => register_mask(y),
+            Self::Shr(x, y) | Self::Shl(x, y) => register_mask(quirks.shift_register(x, y)),
+            Self::JpV0Addr(addr) => register_mask(quirks.jump_register(addr)),
+            Self::LdIVx(x) | Self::LdRVx(x) => register_range_mask(x),
+            _ => 0,
+        }
+    }
+
+    /// Bitmask of the general purpose registers written by this instruction.
+    ///
+    /// Bit `n` is set if register `Vn` is modified, including writes to the VF flag, which depends
+    /// on the `quirks`.
+    pub fn registers_written(&self, quirks: &Quirks) -> u16 {
+        match *self {
+            Self::LdVxByte(x, _)
+            | Self::AddVxByte(x, _)
+            | Self::LdVxVy(x, _)
+            | Self::Rnd(x, _)
+            | Self::LdVxDt(x)
+            | Self::LdVxK(x) => register_mask(x),
+            Self::Or(x, _)
+            | Self::And(x, _)
+            | Self::Xor(x, _)
+            | Self::AddVxVy(x, _)
+            | Self::Sub(x, _)
+            | Self::Shr(x, _)
+            | Self::Subn(x, _)
+            | Self::Shl(x, _) => register_mask(x) | register_mask(0xF),
+            Self::Drw(..) => register_mask(0xF),
+            Self::AddIVx(_) if quirks.index_overflow_flag => register_mask(0xF),
+            Self::LdVxI(x) | Self::LdVxR(x) => register_range_mask(x),
+            _ => 0,
+        }
+    }
+}
+
+/// Bitmask with only the bit of register `Vx` set.
+fn register_mask(x: u8) -> u16 {
+    1 << x
+}
+
+/// Bitmask with the bits of registers `V0` through `Vx` set.
+fn register_range_mask(x: u8) -> u16 {
+    u16::MAX >> (15 - x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_8xy9_depends_on_strict_decode() {
+        assert_eq!(Instruction::decode(0x8129, false), Ok(Instruction::Nop));
+        assert_eq!(
+            Instruction::decode(0x8129, true),
+            Err(Chip8Error::InvalidOpcode {
+                opcode: 0x8129,
+                expected: Some("8xy0 to 8xy7 or 8xyE"),
+            })
+        );
+    }
+
+    #[test]
+    fn drw_costs_more_cycles_than_ld() {
+        assert!(
+            Instruction::Drw(0, 0, 1).machine_cycles()
+                > Instruction::LdVxByte(0, 0).machine_cycles()
+        );
+        assert!(
+            Instruction::Drw(0, 0, 15).machine_cycles()
+                > Instruction::Drw(0, 0, 1).machine_cycles()
+        );
+    }
+
+    #[test]
+    fn undefined_00e1_is_not_a_sys_call() {
+        assert_eq!(Instruction::decode(0x00E1, false), Ok(Instruction::Nop));
+        assert_eq!(
+            Instruction::decode(0x00E1, true),
+            Err(Chip8Error::InvalidOpcode {
+                opcode: 0x00E1,
+                expected: Some("00E0 or 00EE"),
+            })
+        );
+        assert_eq!(Instruction::decode(0x00E0, true), Ok(Instruction::Cls));
+        assert_eq!(Instruction::decode(0x00EE, true), Ok(Instruction::Ret));
+    }
+}
@@ -1,18 +1,138 @@
 use std::{
+    collections::{BTreeMap, HashSet},
     fmt::Write,
+    io,
+    ops::RangeInclusive,
     sync::{Arc, RwLock},
     time::{Duration, Instant},
 };
 
-use crate::{instruction::Instruction, window::Window};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "sdl")]
+use crate::window::Keymap;
+use crate::{
+    cdp1802::Cdp1802,
+    error::Chip8Error,
+    extensions::{ExtensionContext, ExtensionHandler, OpcodePattern},
+    instruction::Instruction,
+    quirks::Quirks,
+    window::{FrameBuffer, HostEvent, Resolution, Window},
+};
+
+/// Effects of a single instruction executed by [`Interpreter::step`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepOutcome {
+    /// The executed instruction.
+    pub instruction: Instruction,
+    /// Whether the screen was cleared or drawn to.
+    pub drew: bool,
+    /// Whether the beep started or stopped.
+    pub sound_changed: bool,
+    /// Whether the instruction waited for a key press (`Fx0A`).
+    pub waited_for_key: bool,
+    /// Whether the instruction drew a sprite while break-on-draw is enabled, see
+    /// [`Interpreter::set_break_on_draw`].
+    pub break_on_draw: bool,
+}
+
+/// Reason why [`Interpreter::run_budget`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    /// The whole budget was used up, the ROM can be resumed by running it again.
+    BudgetExhausted,
+    /// The end of the program was reached.
+    Halted,
+    /// The timeout set with [`Interpreter::set_timeout`] was reached.
+    TimedOut,
+    /// A sprite was drawn while break-on-draw is enabled, see [`Interpreter::set_break_on_draw`].
+    BreakOnDraw,
+}
+
+/// Handling of opcodes, which cannot be decoded (e.g. data interleaved with code).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownOpcodePolicy {
+    /// Halt with [`Chip8Error::InvalidOpcode`].
+    #[default]
+    Halt,
+    /// Skip the opcode, reporting each address once.
+    Warn,
+    /// Silently skip the opcode, treating it as [`Instruction::Nop`].
+    Ignore,
+}
+
+impl UnknownOpcodePolicy {
+    /// Returns the policy with the given `name`, either `halt`, `warn` or `ignore`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "halt" => Some(Self::Halt),
+            "warn" => Some(Self::Warn),
+            "ignore" => Some(Self::Ignore),
+            _ => None,
+        }
+    }
+}
 
 /// Total size of the available memory.
 /// 4KB in total.
-const RAM_SIZE: usize = 0x1000;
+pub const RAM_SIZE: usize = 0x1000;
 
 /// Start of the program.
 /// The bytes before are traditionally taken up by the interpreter
-const PROGRAM_START: usize = 0x200;
+pub const PROGRAM_START: usize = 0x200;
+
+/// Maximum size of a ROM, which has to fit into the memory after [`PROGRAM_START`].
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - PROGRAM_START;
+
+/// Address at which the ETI 660 loads and starts programs.
+pub const ETI_660_START_ADDRESS: u16 = 0x600;
+
+/// Start of the built-in font.
+/// Most interpreters place the digits at 0x050, within the memory reserved for the interpreter.
+const FONT_START: usize = 0x050;
+
+/// Start of the large SUPER-CHIP font, directly after the built-in font.
+const BIG_FONT_START: usize = FONT_START + 16 * 5;
+
+/// First instruction of two-page hires CHIP-8 ROMs, which jumps to the display setup at `0x260`.
+const TWO_PAGE_ENTRY: u16 = 0x1260;
+
+/// Jump to `0x2C0`, where the program of two-page hires CHIP-8 ROMs starts.
+const TWO_PAGE_PROGRAM_JUMP: u16 = 0x12C0;
+
+/// Machine code routine clearing the screen of the two-page hires CHIP-8 variant.
+const TWO_PAGE_CLEAR: u16 = 0x230;
+
+/// Address of V0 to VF in the memory of a 4 KB COSMAC VIP, where machine code routines access
+/// the registers.
+const VIP_REGISTERS: usize = 0xEF0;
+
+/// Initial stack pointer (R2) of machine code routines on a 4 KB COSMAC VIP.
+const VIP_STACK: u16 = 0xECF;
+
+/// Number of machine cycles after which a machine code routine is considered stuck.
+const NATIVE_CYCLE_LIMIT: u64 = 100 * VIP_CYCLES_PER_FRAME;
+
+/// Number of machine cycles the COSMAC VIP executes per 60 Hz frame.
+///
+/// The VIP runs at 1.76 MHz with 8 clock cycles per machine cycle.
+const VIP_CYCLES_PER_FRAME: u64 = 1_760_000 / 8 / 60;
+
+/// Default number of instructions executed per second.
+const DEFAULT_CLOCK_HZ: u32 = 700;
+
+/// Change of the clock rate per press of a speed host key.
+const CLOCK_STEP_HZ: u32 = 100;
+
+/// Range the speed host keys can set the clock rate to.
+const CLOCK_RANGE_HZ: RangeInclusive<u32> = 100..=10_000;
+
+/// Seed of the random number generator used by [`Interpreter::run_digest`].
+const DIGEST_SEED: u64 = 0;
+
+/// Interval at which the status line is updated.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
 
 /// VF register.
 ///
@@ -38,104 +158,893 @@ pub struct Interpreter {
     /// If non-zero, it is automatically decremented at a rate of 60 Hz.
     /// When non-zero, a sound is played.
     sound_register: u8,
+    /// Number of frames the beep is still played for.
+    ///
+    /// Follows the sound register, but is never shorter than [`Self::min_beep_frames`].
+    beep_frames: u8,
+    /// Minimum number of frames a beep is played for, once started.
+    min_beep_frames: u8,
+    /// Whether the window was last told to play the beep.
+    sound_playing: bool,
     /// Special timer register.
     ///
     /// If non-zero, it is automatically decremented at a rate of 60 Hz.
     timer_register: u8,
     /// Stack pointer
     ///
-    /// Points to the topmost level of the stack.
+    /// Points to the next free level of the stack, i.e. the number of nested subroutines.
     stack_pointer: u8,
     /// Stack
     ///
     /// Stores the addresses that should be returned to once finished with a subroutine.
     stack: [u16; 16],
-    /// Fame Buffer of the current window.
+    /// RPL user flags of the HP-48, which SUPER-CHIP ROMs use to save e.g. high scores.
     ///
-    /// Since the interpreter only supports a max width of 64 pixel,
-    /// `u64`s are (mis-)used as bitfields.
-    frame_buffer: Arc<RwLock<[u64; Window::HEIGHT]>>,
+    /// Kept by [`Self::reset_soft`], like on the calculator.
+    rpl_flags: [u8; 8],
+    /// Fame Buffer of the current window.
+    frame_buffer: Arc<RwLock<FrameBuffer>>,
     /// Window that is used to display sprites, etc.
     window: Window,
+    /// Instruction count ([`Self::cycle_count`]) up to which each key is held down by
+    /// [`Self::inject_key_tap`].
+    key_tap_release: [u64; 16],
+    /// Total number of instructions executed since the interpreter was created.
+    cycle_count: u64,
+    /// Total number of frames drawn since the interpreter was created.
+    frame_count: u64,
+    /// Whether the screen is redrawn after every instruction, instead of only after draw calls.
+    redraw_every_instruction: bool,
+    /// Whether each sprite collision is logged to stderr.
+    log_collisions: bool,
+    /// Whether control is returned to the caller after each draw call.
+    break_on_draw: bool,
+    /// Whether a status line with the execution speed is periodically printed to stderr.
+    show_status: bool,
+    /// Total number of modeled COSMAC VIP machine cycles executed since the interpreter was created.
+    machine_cycles: u64,
+    /// Whether execution is throttled to the modeled COSMAC VIP machine cycles.
+    vip_timing: bool,
+    /// Number of instructions executed per second, unlimited if `None`.
+    ///
+    /// Ignored if [`Self::vip_timing`] is enabled.
+    clock_hz: Option<u32>,
+    /// Custom extensions, handling opcodes which are not part of the instruction set.
+    extensions: Vec<(OpcodePattern, ExtensionHandler)>,
+    /// Mnemonics of the instruction families which are allowed to run, all if `None`.
+    allowed_mnemonics: Option<Vec<String>>,
+    /// Random number generator used by [`Instruction::Rnd`].
+    rng: StdRng,
+    /// Wall-clock duration after which execution is stopped.
+    timeout: Option<Duration>,
+    /// Whether the interpreter is reset and the ROM restarted once it reaches its end.
+    loop_on_halt: bool,
+    /// ROM file that is loaded into memory on a reset.
+    rom_file: Vec<u8>,
+    /// Whether opcodes with an undefined low nibble are rejected instead of ignored.
+    ///
+    /// Also rejects jumps into the memory reserved for the interpreter.
+    strict_decode: bool,
+    /// Whether the SUPER-CHIP instructions are decoded, instead of treating them like the VIP.
+    super_chip_instructions: bool,
+    /// Whether jumps and calls to odd addresses are rejected.
+    enforce_alignment: bool,
+    /// Addresses of the jumps into the reserved memory, which have already been reported.
+    reported_jumps: HashSet<u16>,
+    /// Handling of opcodes, which cannot be decoded.
+    unknown_opcode_policy: UnknownOpcodePolicy,
+    /// Addresses of the skipped unknown opcodes, which have already been reported.
+    reported_opcodes: HashSet<u16>,
+    /// Whether reads from registers that have never been written should be reported.
+    warn_uninitialized: bool,
+    /// Whether memory accesses past the end of the memory wrap around, instead of failing.
+    wrap_memory: bool,
+    /// Whether the end of the program has been reached.
+    halted: bool,
+    /// Bitmask of the registers that have been written since the last reset.
+    written_registers: u16,
+    /// Bitmask of the registers for which an uninitialized read has already been reported.
+    warned_registers: u16,
+    /// Behavior of the instructions, which differs between implementations.
+    quirks: Quirks,
+    /// Address at which the ROM is loaded and execution starts.
+    start_address: u16,
+    /// Whether the 64x64 display of the two-page hires CHIP-8 variant is used.
+    two_page_display: bool,
+    /// Whether `0nnn` runs the machine code routine at `nnn` on a [`Cdp1802`], instead of
+    /// calling it like a CHIP-8 subroutine.
+    vip_native: bool,
+    /// Bytes written to the given addresses on every reset, before the ROM is loaded.
+    initial_memory: Vec<(usize, Vec<u8>)>,
+    /// Number of executed instructions per instruction family, if profiling is enabled.
+    profile: Option<BTreeMap<&'static str, u64>>,
 }
 
 impl Interpreter {
     /// Create a new interpreter with the given rom file.
+    ///
+    /// The window is only opened by [`Self::execute`], so the interpreter can also be driven
+    /// headlessly, e.g. with [`Self::step`].
     pub fn new(rom_file: Vec<u8>) -> Self {
         // set up a shared frame buffer between window and interpreter
-        let frame_buffer = Arc::new(RwLock::new([0; Window::HEIGHT]));
+        let frame_buffer = Arc::new(RwLock::new(FrameBuffer::default()));
 
         let mut interpreter = Self {
             memory: vec![0u8; RAM_SIZE],
             registers: [0; 16],
             address_register: 0,
             sound_register: 0,
+            beep_frames: 0,
+            min_beep_frames: 0,
+            sound_playing: false,
             timer_register: 0,
             stack_pointer: 0,
             program_counter: PROGRAM_START as u16,
             stack: [0; 16],
+            rpl_flags: [0; 8],
             window: Window::new(Arc::clone(&frame_buffer)),
             frame_buffer,
+            key_tap_release: [0; 16],
+            cycle_count: 0,
+            frame_count: 0,
+            redraw_every_instruction: false,
+            log_collisions: false,
+            break_on_draw: false,
+            show_status: false,
+            machine_cycles: 0,
+            vip_timing: false,
+            clock_hz: Some(DEFAULT_CLOCK_HZ),
+            extensions: Vec::new(),
+            allowed_mnemonics: None,
+            rng: StdRng::from_entropy(),
+            timeout: None,
+            loop_on_halt: false,
+            rom_file,
+            strict_decode: false,
+            super_chip_instructions: true,
+            enforce_alignment: false,
+            reported_jumps: HashSet::new(),
+            unknown_opcode_policy: UnknownOpcodePolicy::default(),
+            reported_opcodes: HashSet::new(),
+            warn_uninitialized: false,
+            wrap_memory: false,
+            halted: false,
+            written_registers: 0,
+            warned_registers: 0,
+            profile: None,
+            quirks: Quirks::default(),
+            start_address: PROGRAM_START as u16,
+            two_page_display: false,
+            vip_native: false,
+            initial_memory: Vec::new(),
         };
+        interpreter.reset();
+        interpreter
+    }
+
+    /// Creates an [`InterpreterBuilder`] to configure an interpreter for the given rom file.
+    pub fn builder(rom_file: Vec<u8>) -> InterpreterBuilder {
+        InterpreterBuilder::new(rom_file)
+    }
+
+    /// Create a new interpreter with the given rom file and [`Quirks`].
+    pub fn with_quirks(rom_file: Vec<u8>, quirks: Quirks) -> Self {
+        let mut interpreter = Self::new(rom_file);
+        interpreter.quirks = quirks;
+        interpreter
+    }
+
+    /// Resets the interpreter to its initial state and reloads the current rom file.
+    ///
+    /// Clears the RPL user flags as well, see [`Self::reset_soft`] to keep them.
+    pub fn reset(&mut self) {
+        self.rpl_flags = [0; 8];
+        self.reset_soft();
+    }
+
+    /// Resets the interpreter like [`Self::reset`], but keeps the RPL user flags.
+    ///
+    /// Mirrors resetting the HP-48, where the flags survive, so e.g. high scores saved by a
+    /// SUPER-CHIP ROM are still available after restarting it.
+    pub fn reset_soft(&mut self) {
+        self.memory.fill(0);
+        self.registers = [0; 16];
+        self.address_register = 0;
+        self.sound_register = 0;
+        self.beep_frames = 0;
+        self.timer_register = 0;
+        self.stack_pointer = 0;
+        self.program_counter = self.start_address;
+        self.stack = [0; 16];
+        self.halted = false;
+        self.written_registers = 0;
+        self.warned_registers = 0;
+        self.reported_jumps.clear();
+        self.reported_opcodes.clear();
+        self.window.clear();
+        self.frame_buffer
+            .write()
+            .unwrap()
+            .set_resolution(self.base_resolution());
+        self.sound_playing = false;
+        self.window.control_sound(false);
 
         // write font bytes into interpreter memory
         for (idx, digit) in Window::DIGITS.iter().enumerate() {
-            interpreter.write_bytes(idx * digit.len(), digit);
+            self.load_bytes(FONT_START + idx * digit.len(), digit);
+        }
+        for (idx, digit) in Window::BIG_DIGITS.iter().enumerate() {
+            self.load_bytes(BIG_FONT_START + idx * digit.len(), digit);
         }
+        let initial_memory = std::mem::take(&mut self.initial_memory);
+        for (address, bytes) in &initial_memory {
+            self.load_bytes(*address, bytes);
+        }
+        self.initial_memory = initial_memory;
         // write rom file into memory
-        interpreter.write_bytes(PROGRAM_START, &rom_file);
-        interpreter
+        let rom_file = std::mem::take(&mut self.rom_file);
+        self.load_bytes(self.start_address as usize, &rom_file);
+        self.rom_file = rom_file;
+        // the display setup of two-page hires ROMs is machine code, so it is skipped
+        if self.two_page_display
+            && self.read_u16(self.start_address as usize).ok() == Some(TWO_PAGE_ENTRY)
+        {
+            self.load_bytes(
+                self.start_address as usize,
+                &TWO_PAGE_PROGRAM_JUMP.to_be_bytes(),
+            );
+        }
+    }
+
+    /// Resolution of the screen after a reset or `00FE`.
+    fn base_resolution(&self) -> Resolution {
+        if self.two_page_display {
+            Resolution::TwoPage
+        } else {
+            Resolution::Low
+        }
+    }
+
+    /// Replaces the current rom file with `rom_file` and resets the interpreter.
+    ///
+    /// Fails with [`Chip8Error::RomTooLarge`] if the ROM does not fit into the memory after the
+    /// start address, leaving the current ROM untouched.
+    pub fn load_rom(&mut self, rom_file: Vec<u8>) -> Result<(), Chip8Error> {
+        let available = RAM_SIZE.saturating_sub(self.start_address as usize);
+        if rom_file.len() > available {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom_file.len(),
+                available,
+            });
+        }
+        self.rom_file = rom_file;
+        self.reset_soft();
+        Ok(())
+    }
+
+    /// Checks if the `rom` contains any valid instruction before its first `0000` word.
+    ///
+    /// The interpreter stops at the first `0000` word, so an empty or all-zero ROM never
+    /// runs anything.
+    pub fn has_instructions(rom: &[u8]) -> bool {
+        rom.chunks(2)
+            .map(|word| u16::from_be_bytes([word[0], word.get(1).copied().unwrap_or_default()]))
+            .take_while(|&word| word != 0)
+            .any(|word| Instruction::try_from(word).is_ok())
+    }
+
+    /// Parses a ROM from a string of hex bytes, e.g. `"00E0 A22A 600C"`.
+    ///
+    /// Whitespace between the digits is ignored.
+    pub fn parse_rom_hex(text: &str) -> Result<Vec<u8>, Chip8Error> {
+        let digits = text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                c.to_digit(16)
+                    .map(|digit| digit as u8)
+                    .ok_or(Chip8Error::InvalidHexDigit(c))
+            })
+            .collect::<Result<Vec<u8>, Chip8Error>>()?;
+        if digits.is_empty() {
+            return Err(Chip8Error::EmptyRom);
+        }
+        if digits.len() % 2 != 0 {
+            return Err(Chip8Error::OddHexDigitCount(digits.len()));
+        }
+        Ok(digits
+            .chunks(2)
+            .map(|pair| (pair[0] << 4) | pair[1])
+            .collect())
+    }
+
+    /// Registers a custom extension for opcodes matching `pattern`.
+    ///
+    /// The `handler` is only called for opcodes which are not part of the instruction set
+    /// (or reserved), extensions registered first take precedence.
+    pub fn register_extension(&mut self, pattern: OpcodePattern, handler: ExtensionHandler) {
+        self.extensions.push((pattern, handler));
+    }
+
+    /// Returns the index of the first extension handling the `opcode`.
+    fn find_extension(&self, opcode: u16) -> Option<usize> {
+        self.extensions
+            .iter()
+            .position(|(pattern, _)| pattern.matches(opcode))
+    }
+
+    /// Restricts execution to the instruction families with the given mnemonics
+    /// (see [`Instruction::mnemonic`]), any other instruction halts the interpreter.
+    ///
+    /// All instructions are allowed if `None`.
+    pub fn set_allowed_mnemonics(&mut self, mnemonics: Option<Vec<String>>) {
+        self.allowed_mnemonics = mnemonics;
+    }
+
+    /// Seeds the random number generator, making [`Instruction::Rnd`] deterministic.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Sets the wall-clock duration after which execution is stopped, regardless of the ROM.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Enables or disables restarting the ROM once it reaches its end.
+    pub fn set_loop_on_halt(&mut self, enabled: bool) {
+        self.loop_on_halt = enabled;
+    }
+
+    /// Presses `key` for the next `cycles` instructions, then releases it again.
+    ///
+    /// This simulates a tap on the keypad without a window, e.g. when running headless.
+    pub fn inject_key_tap(&mut self, key: u8, cycles: u64) {
+        self.key_tap_release[key as usize & 0xF] = self.cycle_count + cycles;
+    }
+
+    /// Enables or disables redrawing the screen after every instruction.
+    ///
+    /// This is slow, as every redraw waits for the next frame, but makes it possible to follow
+    /// how the screen is composed.
+    pub fn set_redraw_every_instruction(&mut self, enabled: bool) {
+        self.redraw_every_instruction = enabled;
+    }
+
+    /// Enables or disables counting the executed instructions per instruction family.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(BTreeMap::new);
+    }
+
+    /// Enables or disables logging each sprite collision, with its coordinates and rows, to stderr.
+    pub fn set_log_collisions(&mut self, enabled: bool) {
+        self.log_collisions = enabled;
+    }
+
+    /// Enables or disables breaking after each draw call (`Dxyn`), disabled by default.
+    ///
+    /// If enabled, [`Self::step`] reports the break in [`StepOutcome::break_on_draw`] and
+    /// [`Self::run_budget`] returns [`RunStatus::BreakOnDraw`], so a debugger can inspect the frame
+    /// buffer after each sprite.
+    pub fn set_break_on_draw(&mut self, enabled: bool) {
+        self.break_on_draw = enabled;
+    }
+
+    /// Enables or disables the status line, which shows the number of executed instructions,
+    /// the current speed and the number of drawn frames.
+    pub fn set_show_status(&mut self, enabled: bool) {
+        self.show_status = enabled;
+    }
+
+    /// Enables or disables the COSMAC VIP timing model.
+    ///
+    /// If enabled, each instruction is charged its [`Instruction::machine_cycles`] and execution
+    /// is throttled to the number of machine cycles the VIP executes per frame.
+    pub fn set_vip_timing(&mut self, enabled: bool) {
+        self.vip_timing = enabled;
+    }
+
+    /// Sets the number of instructions executed per second, `None` runs as fast as possible.
+    ///
+    /// The instructions are executed in batches, one per 60 Hz frame. Defaults to 700 Hz.
+    pub fn set_clock_hz(&mut self, clock_hz: Option<u32>) {
+        self.clock_hz = clock_hz;
+    }
+
+    /// Enables or disables strict decoding.
+    ///
+    /// If enabled, opcodes with an undefined low nibble (e.g. `8xy9` or `00E1`) halt the interpreter,
+    /// otherwise they are ignored. Jumps below the start address (usually [`PROGRAM_START`]) halt
+    /// the interpreter as well, instead of only being reported.
+    pub fn set_strict_decode(&mut self, enabled: bool) {
+        self.strict_decode = enabled;
+    }
+
+    /// Enables or disables running `0nnn` machine code routines on an emulated CDP1802.
+    ///
+    /// The routines see V0 to VF at `0xEF0` and I in RA, like on a 4 KB COSMAC VIP, and return
+    /// with `SEP R4` (`D4`). Disabled by default, `0nnn` then calls `nnn` like `2nnn`.
+    pub fn set_vip_native(&mut self, enabled: bool) {
+        self.vip_native = enabled;
+    }
+
+    /// Sets the handling of opcodes, which cannot be decoded, defaults to
+    /// [`UnknownOpcodePolicy::Halt`].
+    ///
+    /// Opcodes with an undefined low nibble are only considered unknown with strict decoding,
+    /// see [`Self::set_strict_decode`].
+    pub fn set_unknown_opcode_policy(&mut self, policy: UnknownOpcodePolicy) {
+        self.unknown_opcode_policy = policy;
+    }
+
+    /// Enables or disables the SUPER-CHIP instructions, which are enabled by default.
+    ///
+    /// If disabled, their opcodes are decoded like on the COSMAC VIP, i.e. `00Cn` and `00Fx` as
+    /// machine code routines and `Fx30` as an invalid opcode.
+    pub fn set_super_chip_instructions(&mut self, enabled: bool) {
+        self.super_chip_instructions = enabled;
+    }
+
+    /// Enables or disables rejecting jumps and calls to odd addresses.
+    ///
+    /// Instructions are normally 2-byte aligned, but some ROMs intentionally jump to odd
+    /// addresses, so this is disabled by default.
+    pub fn set_enforce_alignment(&mut self, enabled: bool) {
+        self.enforce_alignment = enabled;
+    }
+
+    /// Enables or disables warnings for reads from registers that have never been written.
+    ///
+    /// Each register is only reported once.
+    pub fn set_warn_uninitialized(&mut self, enabled: bool) {
+        self.warn_uninitialized = enabled;
+    }
+
+    /// Enables or disables wrapping memory accesses past the end of the memory around to `0x000`.
+    ///
+    /// If enabled, the program counter and I also wrap around. If disabled, such accesses (e.g. a
+    /// sprite at I = `0xFFE`) fail with [`Chip8Error::OutOfBounds`].
+    pub fn set_wrap_memory(&mut self, enabled: bool) {
+        self.wrap_memory = enabled;
+    }
+
+    /// Sets the desired audio sample rate in Hz.
+    pub fn set_audio_rate(&mut self, audio_rate: i32) {
+        self.window.set_audio_rate(audio_rate);
+    }
+
+    /// Enables or disables the visual sound indicator, which is shown while the sound is playing.
+    pub fn set_visual_sound(&mut self, enabled: bool) {
+        self.window.set_visual_sound(enabled);
+    }
+
+    /// Enables or disables slowly cycling the foreground color through all hues.
+    ///
+    /// Purely cosmetic, the frame buffer is not affected.
+    pub fn set_rainbow(&mut self, enabled: bool) {
+        self.window.set_rainbow(enabled);
+    }
+
+    /// Enables or disables skipping draw calls, which would present the same screen again.
+    ///
+    /// Saves power on static screens, e.g. menus.
+    pub fn set_skip_unchanged_frames(&mut self, enabled: bool) {
+        self.window.set_skip_unchanged_frames(enabled);
+    }
+
+    /// Sets the mapping of host keys to the CHIP-8 keypad, e.g. to extend [`Keymap::default`].
+    #[cfg(feature = "sdl")]
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.window.set_keymap(keymap);
+    }
+
+    /// Sets the minimum number of frames (at 60 Hz) a beep is played for.
+    ///
+    /// Very short beeps can be inaudible, this does not affect the value of the sound register.
+    pub fn set_min_beep_frames(&mut self, frames: u8) {
+        self.min_beep_frames = frames;
     }
 
     /// Executes the current program in memory.
-    pub fn execute(&mut self) -> Result<(), String> {
+    pub fn execute(&mut self) -> Result<(), Chip8Error> {
         self.window.spawn();
 
         // rate at which timer/sound are decreased. Repsondeds to 60Hz, ~16.67ms
         let timer_cycle = Duration::from_secs_f64(1.0 / 60.0);
         let mut timer_clock = Instant::now();
+        // start of the current frame and the machine cycle count at which it ends
+        let mut frame_clock = Instant::now();
+        let mut frame_end_cycles = self.machine_cycles + VIP_CYCLES_PER_FRAME;
+        let mut frame_instructions = 0;
+        // whether the screen changed since it was last presented
+        let mut redraw_pending = false;
+        // time and instruction count at which the status line was last updated
+        let mut status_clock = Instant::now();
+        let mut status_cycle_count = self.cycle_count;
+        let started = Instant::now();
 
         loop {
+            if self.is_timed_out(started) {
+                return Ok(());
+            }
+            while let Some(event) = self.window.poll_event() {
+                self.handle_host_event(event)?;
+            }
             if !self.window.is_open() {
+                // the window may have sent a crash report right before its thread finished
+                while let Some(event) = self.window.poll_event() {
+                    self.handle_host_event(event)?;
+                }
                 return Ok(());
             }
-            // fetch next instruction
-            let instruction_bytes = self
-                .read_u16(self.program_counter as usize)
-                .unwrap_or_default();
-            if instruction_bytes == 0 {
+            let Some(StepOutcome { instruction, .. }) = self.step()? else {
                 // likely found last instruction
-                std::thread::yield_now();
+                if self.loop_on_halt {
+                    self.reset_soft();
+                    continue;
+                }
+                // timers are no longer decremented, so the beep would never stop
+                self.beep_frames = 0;
+                self.update_sound();
+                // the last frame before the halt would otherwise never be presented
+                if redraw_pending {
+                    self.window.queue_draw();
+                    self.frame_count += 1;
+                    redraw_pending = false;
+                }
+                // nothing changes anymore, so only check for host events once per frame
+                std::thread::sleep(timer_cycle);
                 continue;
-            }
+            };
+            // the screen is only presented once per frame
+            redraw_pending |= self.needs_redraw(&instruction);
 
-            let instruction = Instruction::try_from(instruction_bytes)?;
-            // cycle until a draw call is found, for which we need to update the screen
-            let is_draw_call = matches!(instruction, Instruction::Drw(..));
-            // step to next instruction
-            self.program_counter += 2;
-            // TODO: stepdown timer regs
-            self.execute_instruction(instruction)?;
+            let display_wait = self.waits_for_display(&instruction);
+            // wait for the next frame once the cycles or instructions of the current one are used up
+            frame_instructions += 1;
+            let frame_done = display_wait
+                || self.frame_budget_used(
+                    frame_instructions,
+                    frame_clock.elapsed(),
+                    frame_end_cycles,
+                );
+            if frame_done {
+                // a frame, which took longer (e.g. on a slow host), is not made up for
+                std::thread::sleep(timer_cycle.saturating_sub(frame_clock.elapsed()));
+                frame_clock = Instant::now();
+                // the remaining cycles of a frame ended early are spent waiting
+                if display_wait {
+                    frame_end_cycles = self.machine_cycles;
+                }
+                frame_end_cycles += VIP_CYCLES_PER_FRAME;
+                frame_instructions = 0;
+                if redraw_pending {
+                    self.window.queue_draw();
+                    self.frame_count += 1;
+                    redraw_pending = false;
+                }
+            }
 
             // decrement timer registers
             let elapsed = timer_clock.elapsed();
             if elapsed >= timer_cycle {
                 // timing registers are not affected by interrupts (e.g. waiting for a keypress)
                 let elapsed_cycles = (elapsed.as_secs_f64() / timer_cycle.as_secs_f64()) as u8;
-                self.timer_register = self.timer_register.saturating_sub(elapsed_cycles);
-                self.sound_register = self.sound_register.saturating_sub(elapsed_cycles);
-                self.window.control_sound(self.sound_register > 0);
-                timer_clock = Instant::now();
+                self.tick_timers(elapsed_cycles);
+                // keep the remainder, so the timers stay at 60 Hz independent of the clock rate
+                timer_clock += timer_cycle * elapsed_cycles as u32;
+            }
+
+            if self.show_status && status_clock.elapsed() >= STATUS_INTERVAL {
+                let speed = instructions_per_second(
+                    self.cycle_count - status_cycle_count,
+                    status_clock.elapsed(),
+                );
+                eprint!(
+                    "\r{} instructions, {speed:.0} instructions/s, {} frames",
+                    self.cycle_count, self.frame_count
+                );
+                status_clock = Instant::now();
+                status_cycle_count = self.cycle_count;
+            }
+        }
+    }
+
+    /// Runs the current program without opening a window.
+    ///
+    /// Each draw call (or every instruction, if [`Self::set_redraw_every_instruction`] is
+    /// enabled) ends a frame: the timers are decremented once and `on_frame` is invoked
+    /// with the frame buffer. Execution stops after `max_frames` frames, or once the end of the
+    /// program is reached.
+    pub fn run_headless_with_frames(
+        &mut self,
+        max_frames: usize,
+        mut on_frame: impl FnMut(&FrameBuffer),
+    ) -> Result<(), Chip8Error> {
+        let mut frames = 0;
+        let started = Instant::now();
+        while frames < max_frames && !self.is_timed_out(started) {
+            let Some(StepOutcome { instruction, .. }) = self.step()? else {
+                return Ok(());
+            };
+            if self.needs_redraw(&instruction) {
+                self.tick_timers(1);
+                on_frame(&self.frame_buffer.read().unwrap());
+                frames += 1;
+                self.frame_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if the `instruction` ends the current frame early.
+    ///
+    /// With the display wait quirk, a draw call waits for the vertical blank interrupt.
+    fn waits_for_display(&self, instruction: &Instruction) -> bool {
+        self.redraw_every_instruction
+            || (self.quirks.display_wait && matches!(instruction, Instruction::Drw(..)))
+    }
+
+    /// Checks if the current frame has used up its cycles or instructions, after executing
+    /// `frame_instructions` instructions in `frame_elapsed`; with VIP timing, the frame ends at
+    /// `frame_end_cycles` machine cycles.
+    fn frame_budget_used(
+        &self,
+        frame_instructions: u32,
+        frame_elapsed: Duration,
+        frame_end_cycles: u64,
+    ) -> bool {
+        match self.clock_hz {
+            _ if self.vip_timing => self.machine_cycles >= frame_end_cycles,
+            Some(clock_hz) => frame_instructions >= clock_hz.div_ceil(60),
+            None => frame_elapsed >= Duration::from_secs_f64(1.0 / 60.0),
+        }
+    }
+
+    /// Checks if the screen has to be redrawn after executing the `instruction`.
+    fn needs_redraw(&self, instruction: &Instruction) -> bool {
+        self.redraw_every_instruction
+            || matches!(
+                instruction,
+                Instruction::Drw(..)
+                    | Instruction::Scd(_)
+                    | Instruction::Scr
+                    | Instruction::Scl
+                    | Instruction::Low
+                    | Instruction::High
+            )
+    }
+
+    /// Checks if the [`Self::timeout`] has passed since `started`, reporting it if so.
+    fn is_timed_out(&self, started: Instant) -> bool {
+        let Some(timeout) = self.timeout else {
+            return false;
+        };
+        let timed_out = started.elapsed() >= timeout;
+        if timed_out {
+            eprintln!("Stopping: timeout of {:.1}s reached", timeout.as_secs_f64());
+        }
+        timed_out
+    }
+
+    /// Runs at most `steps` instructions without opening a window.
+    ///
+    /// Stops early once the end of the program or the timeout is reached. If the ROM is
+    /// restarted once it ends (see [`Self::set_loop_on_halt`]), the restart counts as a step.
+    pub fn run_headless_steps(&mut self, steps: u64) -> Result<(), Chip8Error> {
+        self.run_budget(steps).map(|_| ())
+    }
+
+    /// Runs at most `budget` instructions and yields back to the caller.
+    ///
+    /// Allows driving the interpreter from an external event loop, calling this again resumes
+    /// the ROM where it stopped. Neither opens a window nor decrements the timers. If the ROM
+    /// is restarted once it ends, the restart uses up one instruction of the budget.
+    pub fn run_budget(&mut self, budget: u64) -> Result<RunStatus, Chip8Error> {
+        let started = Instant::now();
+        for _ in 0..budget {
+            if self.is_timed_out(started) {
+                return Ok(RunStatus::TimedOut);
+            }
+            match self.step()? {
+                Some(outcome) if outcome.break_on_draw => return Ok(RunStatus::BreakOnDraw),
+                Some(_) => {}
+                None if self.loop_on_halt => self.reset_soft(),
+                None => return Ok(RunStatus::Halted),
+            }
+        }
+        Ok(RunStatus::BudgetExhausted)
+    }
+
+    /// Runs at most `max_cycles` instructions without opening a window, using a fixed seed,
+    /// and returns the SHA-256 digest of the final state.
+    ///
+    /// The digest covers the memory, all registers, the stack and the frame buffer, giving a
+    /// fingerprint which can be compared across builds.
+    pub fn run_digest(&mut self, max_cycles: u64) -> Result<[u8; 32], Chip8Error> {
+        self.set_seed(DIGEST_SEED);
+        self.run_headless_steps(max_cycles)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(&self.memory);
+        hasher.update(self.registers);
+        hasher.update(self.address_register.to_be_bytes());
+        hasher.update(self.program_counter.to_be_bytes());
+        hasher.update([self.stack_pointer, self.timer_register, self.sound_register]);
+        for address in self.stack {
+            hasher.update(address.to_be_bytes());
+        }
+        let frame_buffer = self.frame_buffer.read().unwrap();
+        for row in frame_buffer.rows() {
+            hasher.update(&row.to_be_bytes()[..frame_buffer.width() / 8]);
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// Checks if both interpreters are in the same state.
+    ///
+    /// Compares the memory, all registers, the stack and the frame buffer, but not the
+    /// configuration, e.g. the quirks or the window options.
+    pub fn state_eq(&self, other: &Self) -> bool {
+        self.memory == other.memory
+            && self.registers == other.registers
+            && self.address_register == other.address_register
+            && self.program_counter == other.program_counter
+            && self.stack_pointer == other.stack_pointer
+            && self.stack == other.stack
+            && self.timer_register == other.timer_register
+            && self.sound_register == other.sound_register
+            && *self.frame_buffer.read().unwrap() == *other.frame_buffer.read().unwrap()
+    }
+
+    /// Fetches, decodes and executes the next instruction.
+    ///
+    /// Returns what the instruction did, or `None` if the end of the program (a `0000` word,
+    /// `00FD` or a jump to itself) was reached, after which [`Self::is_halted`] is `true`.
+    /// Does not open a window, so it can be used to drive the interpreter headlessly; without a
+    /// window no key is pressed.
+    pub fn step(&mut self) -> Result<Option<StepOutcome>, Chip8Error> {
+        // fetch next instruction
+        let instruction_bytes = self.read_u16(self.program_counter as usize)?;
+        // memory past the ROM is zeroed, so running into it means the program has ended; `0000`
+        // would otherwise be a call to the machine code routine at 0x000, which is not supported
+        if instruction_bytes == 0 {
+            self.halted = true;
+            return Ok(None);
+        }
+
+        let decoded =
+            Instruction::decode(instruction_bytes, self.strict_decode).and_then(|instruction| {
+                match instruction {
+                    instruction if !instruction.is_super_chip() || self.super_chip_instructions => {
+                        Ok(instruction)
+                    }
+                    // without SUPER-CHIP, the display instructions are machine code routines
+                    _ if instruction_bytes <= 0x0FFF => Ok(Instruction::Sys(instruction_bytes)),
+                    _ => Err(Chip8Error::InvalidOpcode {
+                        opcode: instruction_bytes,
+                        expected: None,
+                    }),
+                }
+            });
+        let instruction = match decoded {
+            // opcodes, which are not part of the instruction set, may be handled by an extension
+            Ok(Instruction::Nop) | Err(_) if self.find_extension(instruction_bytes).is_some() => {
+                Instruction::Extension(instruction_bytes)
+            }
+            Err(err) if self.unknown_opcode_policy != UnknownOpcodePolicy::Halt => {
+                if self.unknown_opcode_policy == UnknownOpcodePolicy::Warn
+                    && self.reported_opcodes.insert(self.program_counter)
+                {
+                    eprintln!("Warning: skipping {err} at {:#05X}", self.program_counter);
+                }
+                Instruction::Nop
+            }
+            // the two-page hires variant clears its larger screen with a machine code routine
+            Ok(Instruction::Sys(TWO_PAGE_CLEAR)) if self.two_page_display => Instruction::Cls,
+            result => result?,
+        };
+        // a jump to itself can never be left, which many ROMs use to end the program
+        if instruction == Instruction::Exit
+            || instruction == Instruction::JpAddr(self.program_counter)
+        {
+            self.halted = true;
+            return Ok(None);
+        }
+        if let Some(allowed) = &self.allowed_mnemonics {
+            if !allowed
+                .iter()
+                .any(|mnemonic| mnemonic == instruction.mnemonic())
+            {
+                return Err(Chip8Error::DisallowedInstruction {
+                    opcode: instruction_bytes,
+                    mnemonic: instruction.mnemonic(),
+                    address: self.program_counter,
+                });
             }
+        }
+        // step to next instruction
+        self.program_counter += 2;
+        let sound_playing = self.sound_playing;
+        self.execute_instruction(instruction)?;
+        if self.wrap_memory {
+            // keep pointing into the memory, so the addresses cannot overflow
+            self.program_counter %= RAM_SIZE as u16;
+            self.address_register %= RAM_SIZE as u16;
+        }
+        self.cycle_count += 1;
+        self.machine_cycles += instruction.machine_cycles() as u64;
+        if let Some(profile) = &mut self.profile {
+            *profile.entry(instruction.mnemonic()).or_default() += 1;
+        }
+        Ok(Some(StepOutcome {
+            instruction,
+            drew: matches!(
+                instruction,
+                Instruction::Cls
+                    | Instruction::Drw(..)
+                    | Instruction::Scd(_)
+                    | Instruction::Scr
+                    | Instruction::Scl
+                    | Instruction::Low
+                    | Instruction::High
+            ),
+            sound_changed: self.sound_playing != sound_playing,
+            waited_for_key: matches!(instruction, Instruction::LdVxK(_)),
+            break_on_draw: self.break_on_draw && matches!(instruction, Instruction::Drw(..)),
+        }))
+    }
+
+    /// Decrements the timer and sound registers by the given number of 60 Hz `frames`.
+    fn tick_timers(&mut self, frames: u8) {
+        self.timer_register = self.timer_register.saturating_sub(frames);
+        self.sound_register = self.sound_register.saturating_sub(frames);
+        self.beep_frames = self.beep_frames.saturating_sub(frames);
+        self.update_sound();
+    }
 
-            if is_draw_call {
-                self.window.queue_draw();
+    /// Starts or stops the beep, depending on [`Self::beep_frames`].
+    ///
+    /// The window is only notified when the beep starts or stops.
+    fn update_sound(&mut self) {
+        let playing = self.beep_frames > 0;
+        if playing != self.sound_playing {
+            self.sound_playing = playing;
+            self.window.control_sound(playing);
+        }
+    }
+
+    /// Handles an [`HostEvent`] sent by the window.
+    fn handle_host_event(&mut self, event: HostEvent) -> Result<(), Chip8Error> {
+        match event {
+            HostEvent::PasteRom(text) => {
+                if let Err(err) =
+                    Self::parse_rom_hex(&text).and_then(|rom_file| self.load_rom(rom_file))
+                {
+                    eprintln!("Failed to load ROM from clipboard: {err}");
+                }
             }
+            HostEvent::SoftReset => self.reset_soft(),
+            HostEvent::SpeedUp => self.adjust_clock_hz(CLOCK_STEP_HZ as i64),
+            HostEvent::SlowDown => self.adjust_clock_hz(-(CLOCK_STEP_HZ as i64)),
+            HostEvent::Crashed(message) => return Err(Chip8Error::WindowCrashed(message)),
         }
+        Ok(())
+    }
+
+    /// Changes the clock rate by `delta` Hz, clamped to [`CLOCK_RANGE_HZ`], and reports it.
+    ///
+    /// Running as fast as possible counts as the highest clock rate.
+    fn adjust_clock_hz(&mut self, delta: i64) {
+        let clock_hz = self.clock_hz.unwrap_or(*CLOCK_RANGE_HZ.end()) as i64 + delta;
+        let clock_hz =
+            clock_hz.clamp(*CLOCK_RANGE_HZ.start() as i64, *CLOCK_RANGE_HZ.end() as i64) as u32;
+        self.set_clock_hz(Some(clock_hz));
+        eprintln!("Clock rate: {clock_hz} Hz");
     }
 
-    /// Dumps the current memory state to stderr.
-    pub fn dump_memory(&self) {
+    /// Dumps the current memory state to `out`.
+    pub fn dump_memory(&self, out: &mut dyn io::Write) -> io::Result<()> {
         self.memory
             .chunks(16 * 2)
             .map(|bytes| {
@@ -147,82 +1056,389 @@ impl Interpreter {
                     },
                 )
             })
-            .for_each(|line| eprintln!("{}", line));
+            .try_for_each(|line| writeln!(out, "{}", line))
     }
 
-    /// Writes the given bytes to the memory, starting at the given offset.
-    fn write_bytes(&mut self, address: usize, bytes: &[u8]) {
-        self.memory
-            .splice(address..(address + bytes.len()), bytes.iter().copied());
+    /// Dumps the current register state to `out`.
+    pub fn dump_registers(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "PC={:04X} I={:04X} SP={:02X} DT={:02X} ST={:02X}",
+            self.program_counter,
+            self.address_register,
+            self.stack_pointer,
+            self.timer_register,
+            self.sound_register
+        )?;
+        for (idx, value) in self.registers.iter().enumerate() {
+            writeln!(out, "V{idx:X}={value:02X}")?;
+        }
+        Ok(())
     }
 
-    fn read_byte(&self, address: usize) -> Option<&u8> {
-        self.memory.get(address)
+    /// Writes the RPL user flags to `out`, so they can be restored with [`Self::load_rpl_flags`].
+    pub fn save_rpl_flags(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        out.write_all(&self.rpl_flags)
     }
 
-    fn read_u16(&self, address: usize) -> Option<u16> {
-        let bytes = &self.memory[address..=(address + 1)];
-        Some(u16::from_be_bytes(bytes.try_into().ok()?))
+    /// Restores the RPL user flags written by [`Self::save_rpl_flags`] from `input`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if `input` does not contain exactly one byte
+    /// per flag, the current flags are kept then.
+    pub fn load_rpl_flags(&mut self, input: &mut dyn io::Read) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        self.rpl_flags = bytes.as_slice().try_into().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected {} bytes of RPL flags, found {}",
+                    self.rpl_flags.len(),
+                    bytes.len()
+                ),
+            )
+        })?;
+        Ok(())
     }
 
-    fn read_bytes(&self, address: usize, len: usize) -> &[u8] {
-        &self.memory[address..(address + len)]
+    /// General purpose registers V0 to VF.
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
     }
 
-    fn push_subroutine(&mut self, address: u16) {
-        // safe current program counter
-        self.stack_pointer += 1;
-        self.stack[self.stack_pointer as usize] = self.program_counter;
-        // jump to subroutine
-        self.program_counter = address;
+    /// Address register I.
+    pub fn address_register(&self) -> u16 {
+        self.address_register
     }
 
-    fn pop_subroutine(&mut self) {
-        // pop to last address
-        self.program_counter = self.stack[self.stack_pointer as usize];
-        self.stack_pointer -= 1;
+    /// Address of the next instruction.
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
     }
 
-    fn execute_instruction(&mut self, instruction: Instruction) -> Result<(), String> {
-        match instruction {
-            Instruction::Sys(addr) => self.push_subroutine(addr),
-            Instruction::Cls => self.window.clear(),
-            Instruction::Ret => self.pop_subroutine(),
-            Instruction::JpAddr(addr) => self.program_counter = addr,
-            Instruction::Call(addr) => self.push_subroutine(addr),
-            Instruction::SeVxByte(reg, byte) => {
-                if self.registers[reg as usize] == byte {
-                    self.program_counter += 2;
-                }
-            }
-            Instruction::SneVxByte(reg, byte) => {
-                if self.registers[reg as usize] != byte {
-                    self.program_counter += 2;
-                }
-            }
-            Instruction::SeVxVy(reg_x, reg_y) => {
-                if self.registers[reg_x as usize] == self.registers[reg_y as usize] {
-                    self.program_counter += 2;
-                }
-            }
-            Instruction::LdVxByte(reg, byte) => self.registers[reg as usize] = byte,
-            Instruction::AddVxByte(reg, byte) => {
-                self.registers[reg as usize] = self.registers[reg as usize].wrapping_add(byte)
-            }
-            Instruction::LdVxVy(reg_x, reg_y) => {
-                self.registers[reg_x as usize] = self.registers[reg_y as usize]
+    /// Checks if the end of the program has been reached, see [`Self::step`].
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Memory of the interpreter, including the font and the ROM.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Copy of the current frame buffer.
+    pub fn frame_buffer(&self) -> FrameBuffer {
+        *self.frame_buffer.read().unwrap()
+    }
+
+    /// Summarizes the program counter, registers and stack in a few lines.
+    ///
+    /// Only reads plain fields, so it can be used to diagnose a panic of the interpreter.
+    pub fn state_snapshot(&self) -> String {
+        let registers: Vec<String> = self
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| format!("V{idx:X}={value:02X}"))
+            .collect();
+        let stack_end = (self.stack_pointer as usize).min(self.stack.len());
+        let stack: Vec<String> = self.stack[..stack_end]
+            .iter()
+            .map(|addr| format!("{addr:04X}"))
+            .collect();
+        format!(
+            "PC={:04X} I={:04X} SP={:02X} DT={:02X} ST={:02X}\n{}\nstack=[{}]\n",
+            self.program_counter,
+            self.address_register,
+            self.stack_pointer,
+            self.timer_register,
+            self.sound_register,
+            registers.join(" "),
+            stack.join(", ")
+        )
+    }
+
+    /// Renders the current frame buffer as ASCII art.
+    ///
+    /// Lit pixels are drawn as `#`, unlit pixels as `.`, one line per row.
+    pub fn display_ascii(&self) -> String {
+        frame_ascii(&self.frame_buffer.read().unwrap())
+    }
+
+    /// Exports the current frame buffer as an X BitMap (XBM) C source.
+    pub fn export_xbm(&self) -> String {
+        let frame_buffer = self.frame_buffer.read().unwrap();
+        // XBM stores the leftmost pixel of each byte in the least significant bit
+        let bytes: Vec<String> = frame_buffer
+            .rows()
+            .iter()
+            .flat_map(|row| row.to_be_bytes().into_iter().take(frame_buffer.width() / 8))
+            .map(|byte| format!("0x{:02x}", byte.reverse_bits()))
+            .collect();
+        let mut xbm = format!(
+            "#define chip8_width {}\n#define chip8_height {}\nstatic unsigned char chip8_bits[] = {{\n",
+            frame_buffer.width(),
+            frame_buffer.height()
+        );
+        for line in bytes.chunks(12) {
+            let _ = writeln!(xbm, "   {},", line.join(", "));
+        }
+        // remove the trailing comma of the last line
+        xbm.truncate(xbm.len() - 2);
+        xbm.push_str(" };\n");
+        xbm
+    }
+
+    /// Exports the instruction histogram as CSV with an `opcode_family,count` header.
+    ///
+    /// Families are sorted by their mnemonic. Returns `None` if profiling is disabled.
+    pub fn profile_csv(&self) -> Option<String> {
+        let profile = self.profile.as_ref()?;
+        let mut csv = String::from("opcode_family,count\n");
+        for (mnemonic, count) in profile {
+            let _ = writeln!(csv, "{mnemonic},{count}");
+        }
+        Some(csv)
+    }
+
+    /// Renders the sprite of the given `height`, starting at `address`, as ASCII art.
+    ///
+    /// Rows beyond the end of the memory are omitted.
+    pub fn sprite_ascii(&self, address: usize, height: usize) -> String {
+        let start = address.min(self.memory.len());
+        let end = address.saturating_add(height).min(self.memory.len());
+        self.memory[start..end]
+            .iter()
+            .map(|&byte| ascii_row(byte as u128, 8))
+            .collect()
+    }
+
+    /// Writes the given bytes to the memory while resetting, starting at the given offset.
+    ///
+    /// Bytes past the end of the memory are dropped, ROMs which do not fit are already rejected
+    /// when they are loaded by the [`InterpreterBuilder`] or from the clipboard.
+    fn load_bytes(&mut self, address: usize, bytes: &[u8]) {
+        let start = address.min(self.memory.len());
+        self.memory[start..]
+            .iter_mut()
+            .zip(bytes)
+            .for_each(|(dest, &byte)| *dest = byte);
+    }
+
+    /// Memory indices of the `len` bytes starting at `address`.
+    ///
+    /// Fails if the bytes exceed the memory, unless [`Self::wrap_memory`] is enabled.
+    fn memory_indices(
+        &self,
+        address: usize,
+        len: usize,
+    ) -> Result<impl Iterator<Item = usize>, Chip8Error> {
+        let size = self.memory.len();
+        if !self.wrap_memory && address + len > size {
+            return Err(Chip8Error::OutOfBounds { address, len });
+        }
+        Ok((address..address + len).map(move |address| address % size))
+    }
+
+    /// Writes the given bytes to the memory, starting at the given offset.
+    fn write_bytes(&mut self, address: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        for (index, &byte) in self.memory_indices(address, bytes.len())?.zip(bytes) {
+            self.memory[index] = byte;
+        }
+        Ok(())
+    }
+
+    fn read_u16(&self, address: usize) -> Result<u16, Chip8Error> {
+        let bytes = self.read_bytes(address, 2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_bytes(&self, address: usize, len: usize) -> Result<Vec<u8>, Chip8Error> {
+        Ok(self
+            .memory_indices(address, len)?
+            .map(|index| self.memory[index])
+            .collect())
+    }
+
+    /// Target of a `Bnnn` jump to `addr`, offset by V0 or Vx depending on the [`Quirks`].
+    fn jump_v0_target(&self, addr: u16) -> u16 {
+        addr + self.registers[self.quirks.jump_register(addr) as usize] as u16
+    }
+
+    /// Runs the machine code routine at `address` on a [`Cdp1802`], with the registers laid out
+    /// like the COSMAC VIP interpreter does.
+    fn run_native(&mut self, address: u16) -> Result<(), Chip8Error> {
+        self.memory[VIP_REGISTERS..][..16].copy_from_slice(&self.registers);
+        let mut cpu = Cdp1802 {
+            p: 3,
+            x: 2,
+            ..Cdp1802::default()
+        };
+        cpu.r[2] = VIP_STACK;
+        cpu.r[3] = address;
+        cpu.r[5] = self.program_counter;
+        cpu.r[0xA] = self.address_register;
+        let cycles = cpu
+            .run_subroutine(&mut self.memory, NATIVE_CYCLE_LIMIT)
+            .ok_or(Chip8Error::NativeRoutineStuck { address })?;
+        self.machine_cycles += cycles;
+        self.registers
+            .copy_from_slice(&self.memory[VIP_REGISTERS..][..16]);
+        self.address_register = cpu.r[0xA];
+        self.program_counter = cpu.r[5];
+        Ok(())
+    }
+
+    fn push_subroutine(&mut self, address: u16) -> Result<(), Chip8Error> {
+        if self.stack_pointer as usize >= self.stack.len() {
+            return Err(Chip8Error::StackOverflow {
+                address: self.program_counter - 2,
+            });
+        }
+        // safe current program counter
+        self.stack[self.stack_pointer as usize] = self.program_counter;
+        self.stack_pointer += 1;
+        // jump to subroutine
+        self.program_counter = address;
+        Ok(())
+    }
+
+    fn pop_subroutine(&mut self) -> Result<(), Chip8Error> {
+        if self.stack_pointer == 0 {
+            return Err(Chip8Error::StackUnderflow {
+                address: self.program_counter - 2,
+            });
+        }
+        // pop to last address
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        Ok(())
+    }
+
+    /// Checks if the given key is pressed, either in the window or by [`Self::inject_key_tap`].
+    fn is_key_pressed(&mut self, key: u8) -> bool {
+        self.key_tap_release
+            .get(key as usize)
+            .is_some_and(|&release| self.cycle_count < release)
+            || self.window.is_key_pressed(key)
+    }
+
+    /// Returns the lowest key currently held down by [`Self::inject_key_tap`].
+    fn tapped_key(&self) -> Option<u8> {
+        (0..16).find(|&key| self.cycle_count < self.key_tap_release[key as usize])
+    }
+
+    /// Number of frames a beep should be played for, when the sound register is set to `sound`.
+    fn beep_duration(&self, sound: u8) -> u8 {
+        if sound == 0 {
+            return 0;
+        }
+        sound.max(self.min_beep_frames)
+    }
+
+    /// Checks the target of jumps and calls.
+    ///
+    /// Fails on odd targets if alignment is enforced. Jumps into the memory reserved for the
+    /// interpreter (below [`Self::start_address`]), which usually indicate a bug in the ROM or a
+    /// wrong quirk, fail if strict decoding is enabled, otherwise each jump is reported once.
+    fn check_jump_target(&mut self, instruction: &Instruction) -> Result<(), Chip8Error> {
+        let target = match *instruction {
+            Instruction::JpAddr(addr) | Instruction::Call(addr) => addr,
+            Instruction::JpV0Addr(addr) => self.jump_v0_target(addr),
+            _ => return Ok(()),
+        };
+        let source = self.program_counter - 2;
+        if self.enforce_alignment && target % 2 != 0 {
+            return Err(Chip8Error::UnalignedJump {
+                instruction: *instruction,
+                address: source,
+                target,
+            });
+        }
+        if target >= self.start_address {
+            return Ok(());
+        }
+        if self.strict_decode {
+            return Err(Chip8Error::ReservedJump {
+                instruction: *instruction,
+                address: source,
+                target,
+            });
+        }
+        if self.reported_jumps.insert(source) {
+            eprintln!(
+                "Warning: {instruction:?} at {source:#05X} jumps to reserved memory at {target:#05X}"
+            );
+        }
+        Ok(())
+    }
+
+    /// Reports registers read by `instruction` that have not been written yet.
+    fn check_uninitialized_reads(&mut self, instruction: &Instruction) {
+        let uninitialized = instruction.registers_read(&self.quirks) & !self.written_registers;
+        let unreported = uninitialized & !self.warned_registers;
+        for reg in (0..16).filter(|reg| unreported & (1 << reg) != 0) {
+            eprintln!(
+                "Warning: {instruction:?} at {:#05X} reads V{reg:X}, which has not been written yet",
+                self.program_counter - 2
+            );
+        }
+        self.warned_registers |= unreported;
+    }
+
+    fn execute_instruction(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        if self.warn_uninitialized {
+            self.check_uninitialized_reads(&instruction);
+        }
+        self.written_registers |= instruction.registers_written(&self.quirks);
+        self.check_jump_target(&instruction)?;
+
+        match instruction {
+            Instruction::Sys(addr) if self.vip_native => self.run_native(addr)?,
+            Instruction::Sys(addr) => self.push_subroutine(addr)?,
+            Instruction::Cls => self.window.clear(),
+            Instruction::Ret => self.pop_subroutine()?,
+            Instruction::JpAddr(addr) => self.program_counter = addr,
+            Instruction::Call(addr) => self.push_subroutine(addr)?,
+            Instruction::SeVxByte(reg, byte) => {
+                if self.registers[reg as usize] == byte {
+                    self.program_counter += 2;
+                }
+            }
+            Instruction::SneVxByte(reg, byte) => {
+                if self.registers[reg as usize] != byte {
+                    self.program_counter += 2;
+                }
+            }
+            Instruction::SeVxVy(reg_x, reg_y) => {
+                if self.registers[reg_x as usize] == self.registers[reg_y as usize] {
+                    self.program_counter += 2;
+                }
+            }
+            Instruction::LdVxByte(reg, byte) => self.registers[reg as usize] = byte,
+            Instruction::AddVxByte(reg, byte) => {
+                self.registers[reg as usize] = self.registers[reg as usize].wrapping_add(byte)
+            }
+            Instruction::LdVxVy(reg_x, reg_y) => {
+                self.registers[reg_x as usize] = self.registers[reg_y as usize]
             }
             Instruction::Or(reg_x, reg_y) => {
                 self.registers[reg_x as usize] |= self.registers[reg_y as usize];
-                self.registers[REG_VF] = 0;
+                if self.quirks.vf_reset {
+                    self.registers[REG_VF] = 0;
+                }
             }
             Instruction::And(reg_x, reg_y) => {
                 self.registers[reg_x as usize] &= self.registers[reg_y as usize];
-                self.registers[REG_VF] = 0;
+                if self.quirks.vf_reset {
+                    self.registers[REG_VF] = 0;
+                }
             }
             Instruction::Xor(reg_x, reg_y) => {
                 self.registers[reg_x as usize] ^= self.registers[reg_y as usize];
-                self.registers[REG_VF] = 0;
+                if self.quirks.vf_reset {
+                    self.registers[REG_VF] = 0;
+                }
             }
             Instruction::AddVxVy(reg_x, reg_y) => {
                 let res = (self.registers[reg_x as usize] as u16)
@@ -238,7 +1454,7 @@ impl Interpreter {
                 self.registers[REG_VF] = (x >= y) as u8;
             }
             Instruction::Shr(reg_x, reg_y) => {
-                let y = self.registers[reg_y as usize];
+                let y = self.registers[self.quirks.shift_register(reg_x, reg_y) as usize];
                 self.registers[reg_x as usize] = y >> 1;
                 self.registers[REG_VF] = y & 1;
             }
@@ -249,7 +1465,7 @@ impl Interpreter {
                 self.registers[REG_VF] = (y >= x) as u8;
             }
             Instruction::Shl(reg_x, reg_y) => {
-                let y = self.registers[reg_y as usize];
+                let y = self.registers[self.quirks.shift_register(reg_x, reg_y) as usize];
                 self.registers[reg_x as usize] = y << 1;
                 self.registers[REG_VF] = (y >> 7) & 1;
             }
@@ -259,60 +1475,124 @@ impl Interpreter {
                 }
             }
             Instruction::LdIAddr(addr) => self.address_register = addr,
-            Instruction::JpV0Addr(addr) => self.program_counter = addr + self.registers[0] as u16,
+            Instruction::JpV0Addr(addr) => self.program_counter = self.jump_v0_target(addr),
             Instruction::Rnd(reg, byte) => {
-                let rand = rand::random::<u8>();
+                let rand = self.rng.gen::<u8>();
                 self.registers[reg as usize] = rand & byte;
             }
-            Instruction::Drw(reg_x, reg_y, n) => self.draw_sprite(
-                self.registers[reg_x as usize],
-                self.registers[reg_y as usize],
-                n,
-            ),
+            Instruction::Drw(reg_x, reg_y, n) => {
+                self.draw_sprite(
+                    self.registers[reg_x as usize],
+                    self.registers[reg_y as usize],
+                    n,
+                )?;
+            }
             Instruction::Skp(reg) => {
-                if self.window.is_key_pressed(self.registers[reg as usize]) {
+                if self.is_key_pressed(self.registers[reg as usize]) {
                     self.program_counter += 2;
                 }
             }
             Instruction::Sknp(reg) => {
-                if !self.window.is_key_pressed(self.registers[reg as usize]) {
+                if !self.is_key_pressed(self.registers[reg as usize]) {
                     self.program_counter += 2;
                 }
             }
             Instruction::LdVxDt(reg) => self.registers[reg as usize] = self.timer_register,
             Instruction::LdVxK(reg) => {
-                self.registers[reg as usize] = self.window.wait_for_key_press()
+                match self.tapped_key().or_else(|| self.window.poll_key_release()) {
+                    Some(key) => self.registers[reg as usize] = key,
+                    // repeat the instruction until a key is released, so the timers keep running
+                    None => self.program_counter -= 2,
+                }
             }
             Instruction::LdDtVx(reg) => self.timer_register = self.registers[reg as usize],
-            Instruction::LdStVx(reg) => self.sound_register = self.registers[reg as usize],
+            Instruction::LdStVx(reg) => {
+                self.sound_register = self.registers[reg as usize];
+                self.beep_frames = self.beep_duration(self.sound_register);
+                self.update_sound();
+            }
             Instruction::AddIVx(reg) => {
-                self.address_register += self.registers[reg as usize] as u16
+                let address = self
+                    .address_register
+                    .wrapping_add(self.registers[reg as usize] as u16);
+                if self.quirks.index_overflow_flag {
+                    self.registers[REG_VF] = (address > 0xFFF) as u8;
+                }
+                self.address_register = if self.quirks.mask_address_register {
+                    address & 0xFFF
+                } else {
+                    address
+                };
             }
             Instruction::LdFVx(reg) => {
-                self.address_register = (self.registers[reg as usize].wrapping_mul(5)) as u16;
+                // only the lowest nibble selects the digit
+                let digit = (self.registers[reg as usize] & 0xF) as usize;
+                self.address_register = (FONT_START + digit * Window::DIGITS[digit].len()) as u16;
+            }
+            Instruction::LdHfVx(reg) => {
+                let digit = (self.registers[reg as usize] & 0xF) as usize;
+                self.address_register =
+                    (BIG_FONT_START + digit * Window::BIG_DIGITS[digit].len()) as u16;
+            }
+            Instruction::LdRVx(reg) => {
+                // only 8 flags exist, larger register ranges are cut off
+                let len = (reg as usize + 1).min(self.rpl_flags.len());
+                self.rpl_flags[..len].copy_from_slice(&self.registers[..len]);
+            }
+            Instruction::LdVxR(reg) => {
+                let len = (reg as usize + 1).min(self.rpl_flags.len());
+                self.registers[..len].copy_from_slice(&self.rpl_flags[..len]);
             }
+            Instruction::Scd(n) => self.frame_buffer.write().unwrap().scroll_down(n as usize),
+            Instruction::Scr => self.frame_buffer.write().unwrap().scroll_right(4),
+            Instruction::Scl => self.frame_buffer.write().unwrap().scroll_left(4),
+            Instruction::Low => {
+                let resolution = self.base_resolution();
+                self.frame_buffer
+                    .write()
+                    .unwrap()
+                    .set_resolution(resolution)
+            }
+            Instruction::High => self
+                .frame_buffer
+                .write()
+                .unwrap()
+                .set_resolution(Resolution::High),
+            // handled by `step`, as it ends the program
+            Instruction::Exit => {}
             Instruction::LdBVx(reg) => {
                 let val = self.registers[reg as usize];
-                self.write_bytes(self.address_register as usize, &[(val / 100) % 10]);
-                self.write_bytes(self.address_register as usize + 1, &[(val / 10) % 10]);
-                self.write_bytes(self.address_register as usize + 2, &[val % 10]);
+                self.write_bytes(
+                    self.address_register as usize,
+                    &[(val / 100) % 10, (val / 10) % 10, val % 10],
+                )?;
             }
             Instruction::LdIVx(reg) => {
-                self.registers
-                    .into_iter()
-                    .take(reg as usize + 1)
-                    .enumerate()
-                    .for_each(|(i, reg)| {
-                        self.write_bytes((self.address_register as usize) + i, &[reg])
-                    });
-                self.address_register += reg as u16 + 1;
+                let registers = self.registers;
+                self.write_bytes(self.address_register as usize, &registers[..=reg as usize])?;
+                self.address_register += self.quirks.load_store_increment(reg);
             }
             Instruction::LdVxI(reg) => {
-                for i in 0..=(reg as usize) {
-                    self.registers[i] =
-                        *self.read_byte(self.address_register as usize + i).unwrap();
-                }
-                self.address_register += reg as u16 + 1;
+                let values = self.read_bytes(self.address_register as usize, reg as usize + 1)?;
+                self.registers[..=reg as usize].copy_from_slice(&values);
+                self.address_register += self.quirks.load_store_increment(reg);
+            }
+            Instruction::Nop => {}
+            Instruction::Extension(opcode) => {
+                let idx = self
+                    .find_extension(opcode)
+                    .ok_or(Chip8Error::InvalidOpcode {
+                        opcode,
+                        expected: None,
+                    })?;
+                let context = ExtensionContext {
+                    registers: &mut self.registers,
+                    memory: &mut self.memory,
+                    address_register: &mut self.address_register,
+                    program_counter: &mut self.program_counter,
+                };
+                (self.extensions[idx].1)(opcode, context)
+                    .map_err(|message| Chip8Error::Extension { opcode, message })?;
             }
         };
         Ok(())
@@ -320,19 +1600,1527 @@ impl Interpreter {
 
     /// Draw the sprite located at [`Self::address_register`]
     /// to [`Self::address_register`] + `n` starting at (`x`, `y`).
-    fn draw_sprite(&mut self, x: u8, y: u8, n: u8) {
-        let draw_bytes = self
-            .read_bytes(self.address_register as usize, n as usize)
-            .to_vec();
+    /// Returns the screen rows, in which pixels were erased.
+    ///
+    /// In the high resolution mode, `n = 0` draws a 16x16 sprite of 32 bytes instead.
+    fn draw_sprite(&mut self, x: u8, y: u8, n: u8) -> Result<Vec<usize>, Chip8Error> {
+        let resolution = self.frame_buffer.read().unwrap().resolution();
+        let (sprite_width, height) = if n == 0 && resolution == Resolution::High {
+            (16, 16)
+        } else {
+            (8, n as usize)
+        };
+        let bytes_per_row = sprite_width / 8;
+        let draw_bytes = self.read_bytes(self.address_register as usize, height * bytes_per_row)?;
         let mut frame_buffer = self.frame_buffer.write().unwrap();
-        for (i, byte) in draw_bytes.into_iter().enumerate() {
-            let coord = (y as usize + i) % Window::HEIGHT;
-            let original = frame_buffer[coord];
-            // shift an addiontal 8 bits, so the byte is moved to the beginning
-            let res = original ^ (byte as u64).rotate_right(x as u32 + 8);
-            // check if any bits where erased (set to 0)
-            self.registers[REG_VF] = ((original & !res) != 0) as u8;
-            frame_buffer[coord] = res;
+        let x = x as usize % frame_buffer.width();
+        let y = y as usize % frame_buffer.height();
+        let mut collision_rows = Vec::new();
+        for (i, bytes) in draw_bytes.chunks(bytes_per_row).enumerate() {
+            let row = y + i;
+            if self.quirks.clip_sprites && row >= frame_buffer.height() {
+                break;
+            }
+            let coord = row % frame_buffer.height();
+            // move the sprite row to the leftmost pixels
+            let sprite_row = bytes.iter().fold(0, |row, &byte| (row << 8) | byte as u128)
+                << (u128::BITS as usize - sprite_width);
+            // check if any bits where erased (set to 0) in any of the rows
+            if frame_buffer.xor_row(coord, sprite_row, x, self.quirks.clip_sprites) {
+                collision_rows.push(coord);
+            }
+        }
+        self.registers[REG_VF] = !collision_rows.is_empty() as u8;
+        if self.log_collisions && !collision_rows.is_empty() {
+            eprintln!("Collision: sprite at ({x}, {y}) erased pixels in rows {collision_rows:?}");
+        }
+        Ok(collision_rows)
+    }
+}
+
+/// Builder for an [`Interpreter`], configuring it before it is created.
+#[derive(Debug, Clone)]
+pub struct InterpreterBuilder {
+    rom_file: Vec<u8>,
+    clock_hz: Option<u32>,
+    start_address: u16,
+    two_page_display: bool,
+    quirks: Quirks,
+    seed: Option<u64>,
+    initial_memory: Vec<(usize, Vec<u8>)>,
+}
+
+impl InterpreterBuilder {
+    /// Creates a builder for an interpreter running the given rom file.
+    pub fn new(rom_file: Vec<u8>) -> Self {
+        Self {
+            rom_file,
+            clock_hz: Some(DEFAULT_CLOCK_HZ),
+            start_address: PROGRAM_START as u16,
+            two_page_display: false,
+            quirks: Quirks::default(),
+            seed: None,
+            initial_memory: Vec::new(),
+        }
+    }
+
+    /// Sets the number of instructions executed per second, see [`Interpreter::set_clock_hz`].
+    pub fn clock_hz(mut self, clock_hz: Option<u32>) -> Self {
+        self.clock_hz = clock_hz;
+        self
+    }
+
+    /// Sets the address at which the ROM is loaded and execution starts, defaults to `0x200`.
+    ///
+    /// Some interpreters, e.g. the ETI 660, start programs at `0x600` instead
+    /// ([`ETI_660_START_ADDRESS`]). The memory below the start address is reserved for the
+    /// interpreter.
+    pub fn start_address(mut self, start_address: u16) -> Self {
+        self.start_address = start_address;
+        self
+    }
+
+    /// Enables or disables the 64x64 display of the two-page hires CHIP-8 variant.
+    ///
+    /// ROMs of this variant start with a jump to `0x260`, which is replaced by a jump to the
+    /// program at `0x2C0`, and clear the screen by calling `0x230`.
+    pub fn two_page_display(mut self, enabled: bool) -> Self {
+        self.two_page_display = enabled;
+        self
+    }
+
+    /// Sets the [`Quirks`] of the instructions.
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Seeds the random number generator, see [`Interpreter::set_seed`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Writes `bytes` to the memory at `address` on every reset, before the ROM is loaded.
+    ///
+    /// Can be used to provide data in the memory reserved for the interpreter.
+    pub fn memory(mut self, address: usize, bytes: Vec<u8>) -> Self {
+        self.initial_memory.push((address, bytes));
+        self
+    }
+
+    /// Creates the interpreter.
+    ///
+    /// Fails if the start address is outside of the memory, or if the ROM or the initial memory
+    /// contents do not fit into the memory.
+    pub fn build(self) -> Result<Interpreter, Chip8Error> {
+        if self.start_address as usize >= RAM_SIZE {
+            return Err(Chip8Error::OutOfBounds {
+                address: self.start_address as usize,
+                len: self.rom_file.len(),
+            });
+        }
+        if let Some((address, bytes)) = self
+            .initial_memory
+            .iter()
+            .find(|(address, bytes)| address + bytes.len() > RAM_SIZE)
+        {
+            return Err(Chip8Error::OutOfBounds {
+                address: *address,
+                len: bytes.len(),
+            });
+        }
+
+        // the ROM is only loaded once the start address is known
+        let mut interpreter = Interpreter::with_quirks(Vec::new(), self.quirks);
+        interpreter.set_clock_hz(self.clock_hz);
+        if let Some(seed) = self.seed {
+            interpreter.set_seed(seed);
+        }
+        interpreter.start_address = self.start_address;
+        interpreter.two_page_display = self.two_page_display;
+        interpreter.initial_memory = self.initial_memory;
+        interpreter.load_rom(self.rom_file)?;
+        Ok(interpreter)
+    }
+}
+
+/// Average number of instructions executed per second.
+fn instructions_per_second(instructions: u64, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    instructions as f64 / elapsed.as_secs_f64()
+}
+
+/// Renders the given frame buffer as ASCII art, see [`Interpreter::display_ascii`].
+pub fn frame_ascii(frame_buffer: &FrameBuffer) -> String {
+    let width = frame_buffer.width();
+    frame_buffer
+        .rows()
+        .iter()
+        .map(|&row| ascii_row(row >> (u128::BITS as usize - width), width))
+        .collect()
+}
+
+/// Renders the lowest `width` bits of `row` as a line of ASCII art.
+///
+/// Set bits are drawn as `#`, unset bits as `.`.
+fn ascii_row(row: u128, width: usize) -> String {
+    (0..width)
+        .map(|x| {
+            if row & (1 << (width - 1 - x)) != 0 {
+                '#'
+            } else {
+                '.'
+            }
+        })
+        .chain(std::iter::once('\n'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uninitialized_read_is_reported_once() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter.set_warn_uninitialized(true);
+        interpreter
+            .execute_instruction(Instruction::LdVxVy(0, 1))
+            .unwrap();
+        assert_eq!(interpreter.warned_registers, 1 << 1);
+        assert_eq!(interpreter.written_registers, 1 << 0);
+        interpreter
+            .execute_instruction(Instruction::LdVxVy(2, 1))
+            .unwrap();
+        assert_eq!(interpreter.warned_registers, 1 << 1);
+    }
+
+    #[test]
+    fn short_beep_lasts_minimum_frames() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter.set_min_beep_frames(2);
+        interpreter
+            .execute_instruction(Instruction::LdVxByte(0, 0x01))
+            .unwrap();
+        interpreter
+            .execute_instruction(Instruction::LdStVx(0))
+            .unwrap();
+        assert_eq!(interpreter.sound_register, 1);
+        assert_eq!(interpreter.beep_frames, 2);
+        interpreter.tick_timers(1);
+        assert_eq!(interpreter.sound_register, 0);
+        assert_eq!(interpreter.beep_frames, 1);
+        interpreter.tick_timers(1);
+        assert_eq!(interpreter.beep_frames, 0);
+    }
+
+    #[test]
+    fn display_ascii_shows_drawn_sprite() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter
+            .execute_instruction(Instruction::LdFVx(0))
+            .unwrap();
+        interpreter
+            .execute_instruction(Instruction::Drw(0, 0, 5))
+            .unwrap();
+        let ascii = interpreter.display_ascii();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 32);
+        assert!(lines[0].starts_with("####."));
+        assert!(lines[1].starts_with("#..#."));
+        assert!(lines[4].starts_with("####."));
+        assert!(lines[5].chars().all(|pixel| pixel == '.'));
+    }
+
+    #[test]
+    fn parse_rom_hex_accepts_whitespace_separated_bytes() {
+        assert_eq!(
+            Interpreter::parse_rom_hex("00E0 A22A\n600c"),
+            Ok(vec![0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C])
+        );
+        assert!(Interpreter::parse_rom_hex("00E0 A2G").is_err());
+        assert!(Interpreter::parse_rom_hex("00E").is_err());
+        assert!(Interpreter::parse_rom_hex(" \n").is_err());
+    }
+
+    #[test]
+    fn load_store_boundary_registers() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        let registers: [u8; 16] = std::array::from_fn(|x| 0x10 | x as u8);
+        interpreter.registers = registers;
+
+        interpreter.address_register = 0x300;
+        interpreter
+            .execute_instruction(Instruction::LdIVx(0))
+            .unwrap();
+        assert_eq!(interpreter.memory[0x300..0x302], [0x10, 0x00]);
+        assert_eq!(interpreter.address_register, 0x301);
+        interpreter.address_register = 0x310;
+        interpreter
+            .execute_instruction(Instruction::LdIVx(0xF))
+            .unwrap();
+        assert_eq!(interpreter.memory[0x310..0x320], registers);
+        assert_eq!(interpreter.memory[0x320], 0x00);
+        assert_eq!(interpreter.address_register, 0x320);
+
+        let values: Vec<u8> = (0xA0..0xB0).collect();
+        interpreter.load_bytes(0x400, &values);
+        interpreter.address_register = 0x400;
+        interpreter
+            .execute_instruction(Instruction::LdVxI(0))
+            .unwrap();
+        assert_eq!(interpreter.registers[..2], [0xA0, 0x11]);
+        assert_eq!(interpreter.address_register, 0x401);
+        interpreter.address_register = 0x400;
+        interpreter
+            .execute_instruction(Instruction::LdVxI(0xF))
+            .unwrap();
+        assert_eq!(interpreter.registers[..], values);
+        assert_eq!(interpreter.address_register, 0x410);
+    }
+
+    #[test]
+    fn sprite_ascii_renders_font_glyph() {
+        let interpreter = Interpreter::new(Vec::new());
+        // digit 1
+        assert_eq!(
+            interpreter.sprite_ascii(FONT_START + 5, 5),
+            "..#.....\n.##.....\n..#.....\n..#.....\n.###....\n"
+        );
+        // rows past the end of the memory are omitted
+        assert_eq!(interpreter.sprite_ascii(RAM_SIZE - 1, 5).lines().count(), 1);
+    }
+
+    #[test]
+    fn dump_memory_writes_into_buffer() {
+        let interpreter = Interpreter::new(vec![0x63, 0xAB]);
+        let mut memory = Vec::new();
+        interpreter.dump_memory(&mut memory).unwrap();
+        let memory = String::from_utf8(memory).unwrap();
+        assert_eq!(memory.lines().count(), RAM_SIZE / 32);
+        assert!(memory
+            .lines()
+            .nth(PROGRAM_START / 32)
+            .unwrap()
+            .starts_with("63AB00"));
+    }
+
+    #[test]
+    fn frame_callback_runs_once_per_draw() {
+        // LD F, V1; DRW V0, V0, 1; ADD V0, 0x01; JP 0x202
+        let mut interpreter =
+            Interpreter::new(vec![0xF1, 0x29, 0xD0, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        let mut frames = Vec::new();
+        interpreter
+            .run_headless_with_frames(3, |frame| frames.push(*frame))
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        assert!(frames[0].is_set(0, 0));
+        assert!(frames[2].is_set(2, 2));
+    }
+
+    #[test]
+    fn font_digit_zero_starts_at_font_start() {
+        // LD F, V0
+        let mut interpreter = Interpreter::new(vec![0xF0, 0x29]);
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.address_register, FONT_START as u16);
+        assert_eq!(
+            interpreter.memory[FONT_START..FONT_START + 5],
+            Window::DIGITS[0]
+        );
+    }
+
+    #[test]
+    fn tall_sprite_at_bottom_edge_wraps_or_clips() {
+        for clip_sprites in [false, true] {
+            let mut interpreter = Interpreter::with_quirks(
+                Vec::new(),
+                Quirks {
+                    clip_sprites,
+                    ..Quirks::default()
+                },
+            );
+            interpreter.load_bytes(0x300, &[0x80; 15]);
+            interpreter.address_register = 0x300;
+            interpreter.registers[1] = 30;
+            interpreter
+                .execute_instruction(Instruction::Drw(0, 1, 15))
+                .unwrap();
+            {
+                let frame_buffer = interpreter.frame_buffer.read().unwrap();
+                assert!(frame_buffer.is_set(0, 30) && frame_buffer.is_set(0, 31));
+                assert_eq!(frame_buffer.is_set(0, 0), !clip_sprites);
+                assert_eq!(frame_buffer.is_set(0, 12), !clip_sprites);
+                assert!(!frame_buffer.is_set(0, 13));
+            }
+            assert_eq!(interpreter.registers[REG_VF], 0);
+
+            // drawing the same sprite again erases all of its rows
+            interpreter
+                .execute_instruction(Instruction::Drw(0, 1, 15))
+                .unwrap();
+            assert_eq!(interpreter.registers[REG_VF], 1);
+            assert_eq!(
+                *interpreter.frame_buffer.read().unwrap(),
+                FrameBuffer::default()
+            );
         }
     }
+
+    #[test]
+    fn instructions_per_second_uses_elapsed_time() {
+        assert_eq!(
+            instructions_per_second(700, Duration::from_millis(500)),
+            1400.0
+        );
+        assert_eq!(instructions_per_second(700, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn key_tap_is_released_after_cycles() {
+        // LD V0, 0x05; SKP V0; LD V1, 0x01; SKP V0; LD V2, 0x01
+        let mut interpreter = Interpreter::new(vec![
+            0x60, 0x05, 0xE0, 0x9E, 0x61, 0x01, 0xE0, 0x9E, 0x62, 0x01,
+        ]);
+        interpreter.inject_key_tap(0x5, 2);
+        for _ in 0..4 {
+            interpreter.step().unwrap();
+        }
+        assert_eq!(interpreter.registers[1], 0, "pressed during the first skip");
+        assert_eq!(interpreter.registers[2], 1, "released at the second skip");
+    }
+
+    #[test]
+    fn speed_keys_change_clamped_clock_rate() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter.handle_host_event(HostEvent::SpeedUp).unwrap();
+        assert_eq!(interpreter.clock_hz, Some(DEFAULT_CLOCK_HZ + CLOCK_STEP_HZ));
+        interpreter.handle_host_event(HostEvent::SlowDown).unwrap();
+        interpreter.handle_host_event(HostEvent::SlowDown).unwrap();
+        assert_eq!(interpreter.clock_hz, Some(DEFAULT_CLOCK_HZ - CLOCK_STEP_HZ));
+
+        interpreter.set_clock_hz(Some(*CLOCK_RANGE_HZ.start()));
+        interpreter.handle_host_event(HostEvent::SlowDown).unwrap();
+        assert_eq!(interpreter.clock_hz, Some(*CLOCK_RANGE_HZ.start()));
+        // an unlimited clock rate is slowed down from the highest one
+        interpreter.set_clock_hz(None);
+        interpreter.handle_host_event(HostEvent::SpeedUp).unwrap();
+        assert_eq!(interpreter.clock_hz, Some(*CLOCK_RANGE_HZ.end()));
+        interpreter.handle_host_event(HostEvent::SlowDown).unwrap();
+        assert_eq!(
+            interpreter.clock_hz,
+            Some(*CLOCK_RANGE_HZ.end() - CLOCK_STEP_HZ)
+        );
+    }
+
+    #[test]
+    fn window_crash_becomes_error() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        assert_eq!(
+            interpreter.handle_host_event(HostEvent::Crashed("boom".to_string())),
+            Err(Chip8Error::WindowCrashed("boom".to_string()))
+        );
+    }
+
+    #[test]
+    fn headless_run_stops_at_timeout() {
+        // LD F, V0; DRW V0, V0, 1; JP 0x202
+        let mut interpreter = Interpreter::new(vec![0xF0, 0x29, 0xD0, 0x01, 0x12, 0x02]);
+        interpreter.set_timeout(Some(Duration::ZERO));
+        let mut frames = 0;
+        interpreter
+            .run_headless_with_frames(usize::MAX, |_| frames += 1)
+            .unwrap();
+        assert_eq!(frames, 0);
+    }
+
+    #[test]
+    fn register_dump_after_fixed_steps_is_deterministic() {
+        // LD V0, 0x12; RND V1, 0xFF; ADD V0, 0x01
+        let rom = vec![0x60, 0x12, 0xC1, 0xFF, 0x70, 0x01];
+        let dump = || {
+            let mut interpreter = Interpreter::new(rom.clone());
+            interpreter.set_seed(7);
+            interpreter.run_headless_steps(2).unwrap();
+            let mut out = Vec::new();
+            interpreter.dump_registers(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let first = dump();
+        assert!(first.starts_with("PC=0204 "));
+        assert!(first.contains("V0=12\n"));
+        assert_eq!(first, dump());
+    }
+
+    #[test]
+    fn loop_on_halt_restarts_rom() {
+        // ADD V0, 0x01; halt
+        let mut interpreter = Interpreter::new(vec![0x70, 0x01]);
+        interpreter.set_loop_on_halt(true);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.program_counter, PROGRAM_START as u16);
+        assert_eq!(interpreter.registers[0], 0);
+        interpreter.run_headless_steps(1).unwrap();
+        assert_eq!(interpreter.registers[0], 1);
+    }
+
+    /// Draws the 1-row sprite `0xF0` and then `sprite` at the top left corner.
+    fn draw_over(sprite: u8) -> Interpreter {
+        // LD I, 0x300; DRW V0, V0, 1; LD I, 0x301; DRW V0, V0, 1
+        let mut interpreter =
+            Interpreter::new(vec![0xA3, 0x00, 0xD0, 0x01, 0xA3, 0x01, 0xD0, 0x01]);
+        interpreter.load_bytes(0x300, &[0xF0, sprite]);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.registers[REG_VF], 0, "blank screen");
+        interpreter.run_headless_steps(2).unwrap();
+        interpreter
+    }
+
+    /// Returns the leftmost 8 pixels of the first row.
+    fn first_byte(interpreter: &Interpreter) -> u8 {
+        (interpreter.frame_buffer.read().unwrap().rows()[0] >> (u128::BITS - 8)) as u8
+    }
+
+    #[test]
+    fn collision_flag_cases() {
+        let full = draw_over(0xF0);
+        assert_eq!(full.registers[REG_VF], 1);
+        assert_eq!(*full.frame_buffer.read().unwrap(), FrameBuffer::default());
+
+        let partial = draw_over(0x30);
+        assert_eq!(partial.registers[REG_VF], 1);
+        assert_eq!(first_byte(&partial), 0xC0);
+
+        let none = draw_over(0x0F);
+        assert_eq!(none.registers[REG_VF], 0);
+        assert_eq!(first_byte(&none), 0xFF);
+    }
+
+    #[test]
+    fn memory_dump_follows_memory_size() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        let mut out = Vec::new();
+        interpreter.dump_memory(&mut out).unwrap();
+        assert_eq!(out.split(|&byte| byte == b'\n').count() - 1, RAM_SIZE / 32);
+
+        // XO-CHIP sized memory
+        interpreter.memory = vec![0; 0x10000];
+        let mut out = Vec::new();
+        interpreter.dump_memory(&mut out).unwrap();
+        assert_eq!(out.split(|&byte| byte == b'\n').count() - 1, 0x10000 / 32);
+    }
+
+    /// Executes `opcode` with V1 = `vx`, V2 = `vy` and VF = 0x55, returning V1 and VF.
+    fn alu(opcode: u16, vx: u8, vy: u8) -> (u8, u8) {
+        let [high, low] = opcode.to_be_bytes();
+        let mut interpreter = Interpreter::new(vec![0x61, vx, 0x62, vy, 0x6F, 0x55, high, low]);
+        interpreter.run_headless_steps(4).unwrap();
+        (interpreter.registers[1], interpreter.registers[REG_VF])
+    }
+
+    /// Runs the first `steps` instructions of `rom` with the given `quirks`.
+    fn run_with_quirks(quirks: Quirks, rom: Vec<u8>, steps: u64) -> Interpreter {
+        let mut interpreter = Interpreter::builder(rom).quirks(quirks).build().unwrap();
+        interpreter.run_headless_steps(steps).unwrap();
+        interpreter
+    }
+
+    #[test]
+    fn carry_and_borrow() {
+        // ADD V1, V2
+        assert_eq!(alu(0x8124, 0xFF, 0x01), (0x00, 1));
+        assert_eq!(alu(0x8124, 0x00, 0x01), (0x01, 0));
+        // SUB V1, V2
+        assert_eq!(alu(0x8125, 0x00, 0x01), (0xFF, 0));
+        assert_eq!(alu(0x8125, 0xFF, 0x01), (0xFE, 1));
+        assert_eq!(alu(0x8125, 0x01, 0x01), (0x00, 1));
+        // SUBN V1, V2
+        assert_eq!(alu(0x8127, 0x01, 0x00), (0xFF, 0));
+        assert_eq!(alu(0x8127, 0x01, 0xFF), (0xFE, 1));
+        // ADD V1, 0x01 leaves VF untouched
+        assert_eq!(alu(0x7101, 0xFF, 0x00), (0x00, 0x55));
+        // SHR V1, V2 and SHL V1, V2
+        assert_eq!(alu(0x8126, 0x01, 0x01), (0x00, 1));
+        assert_eq!(alu(0x8126, 0xFE, 0xFE), (0x7F, 0));
+        assert_eq!(alu(0x812E, 0xFF, 0xFF), (0xFE, 1));
+        assert_eq!(alu(0x812E, 0x7F, 0x7F), (0xFE, 0));
+    }
+
+    #[test]
+    fn redraw_every_instruction_produces_frame_per_instruction() {
+        // LD V0, 0x01; ADD V0, 0x01; ADD V0, 0x01; halt
+        let rom = vec![0x60, 0x01, 0x70, 0x01, 0x70, 0x01];
+        let mut interpreter = Interpreter::new(rom.clone());
+        let mut frames = 0;
+        interpreter
+            .run_headless_with_frames(10, |_| frames += 1)
+            .unwrap();
+        assert_eq!(frames, 0);
+
+        let mut interpreter = Interpreter::new(rom);
+        interpreter.set_redraw_every_instruction(true);
+        let mut frames = 0;
+        interpreter
+            .run_headless_with_frames(10, |_| frames += 1)
+            .unwrap();
+        assert_eq!(frames, 3);
+    }
+
+    #[test]
+    fn font_glyph_a_is_drawn() {
+        // LD V0, 0x0A; LD F, V0; DRW V1, V1, 5
+        let mut interpreter = Interpreter::new(vec![0x60, 0x0A, 0xF0, 0x29, 0xD1, 0x15]);
+        interpreter.run_headless_steps(3).unwrap();
+        let ascii = interpreter.display_ascii();
+        let glyph: Vec<&str> = ascii.lines().take(6).map(|line| &line[..5]).collect();
+        assert_eq!(
+            glyph,
+            ["####.", "#..#.", "####.", "#..#.", "#..#.", "....."]
+        );
+    }
+
+    #[test]
+    fn disallowed_instruction_halts() {
+        // LD V0, 0x01; ADD V0, 0x01; CLS
+        let mut interpreter = Interpreter::new(vec![0x60, 0x01, 0x70, 0x01, 0x00, 0xE0]);
+        interpreter.set_allowed_mnemonics(Some(vec!["ld".to_string(), "add".to_string()]));
+        assert_eq!(
+            interpreter.run_headless_steps(3),
+            Err(Chip8Error::DisallowedInstruction {
+                opcode: 0x00E0,
+                mnemonic: "cls",
+                address: 0x204,
+            })
+        );
+        assert_eq!(interpreter.program_counter, 0x204);
+    }
+
+    #[test]
+    fn jump_to_reserved_memory_is_reported() {
+        // JP V0, 0x000
+        let mut interpreter = Interpreter::new(vec![0xB0, 0x00]);
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.program_counter, 0x000);
+        assert!(interpreter.reported_jumps.contains(&0x200));
+
+        let mut interpreter = Interpreter::new(vec![0xB0, 0x00]);
+        interpreter.set_strict_decode(true);
+        assert_eq!(
+            interpreter.step(),
+            Err(Chip8Error::ReservedJump {
+                instruction: Instruction::JpV0Addr(0x000),
+                address: 0x200,
+                target: 0x000,
+            })
+        );
+    }
+
+    #[test]
+    fn ret_on_empty_stack_fails() {
+        // RET
+        let mut interpreter = Interpreter::new(vec![0x00, 0xEE]);
+        assert_eq!(interpreter.stack_pointer, 0);
+        assert_eq!(
+            interpreter.step(),
+            Err(Chip8Error::StackUnderflow { address: 0x200 })
+        );
+        assert_eq!(interpreter.stack_pointer, 0);
+    }
+
+    #[test]
+    fn headless_draws_do_not_wait_for_frames() {
+        // DRW V0, V0, 15; JP 0x200
+        let mut interpreter = Interpreter::new(vec![0xD0, 0x0F, 0x12, 0x00]);
+        let started = Instant::now();
+        let mut frames = 0;
+        interpreter
+            .run_headless_with_frames(600, |_| frames += 1)
+            .unwrap();
+        assert_eq!(frames, 600);
+        // 600 frames take 10 seconds at 60 Hz
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn custom_extension_handles_opcode() {
+        // custom FxF0: LD Vx, 0x42
+        let mut interpreter = Interpreter::new(vec![0xF3, 0xF0, 0xF3, 0xF0]);
+        interpreter.register_extension(
+            OpcodePattern {
+                mask: 0xF0FF,
+                value: 0xF0F0,
+            },
+            Box::new(|opcode, context| {
+                if context.registers[(opcode >> 8) as usize & 0xF] == 0x42 {
+                    return Err("already set".to_string());
+                }
+                context.registers[(opcode >> 8) as usize & 0xF] = 0x42;
+                Ok(())
+            }),
+        );
+        let outcome = interpreter.step().unwrap().unwrap();
+        assert_eq!(outcome.instruction, Instruction::Extension(0xF3F0));
+        assert_eq!(interpreter.registers[3], 0x42);
+        assert_eq!(interpreter.program_counter, 0x202);
+        assert_eq!(
+            interpreter.step(),
+            Err(Chip8Error::Extension {
+                opcode: 0xF3F0,
+                message: "already set".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn xbm_export_matches_screen() {
+        // LD F, V0; DRW V0, V0, 2
+        let mut interpreter = Interpreter::new(vec![0xF0, 0x29, 0xD0, 0x02]);
+        interpreter.run_headless_steps(2).unwrap();
+        let xbm = interpreter.export_xbm();
+        assert!(xbm.starts_with(
+            "#define chip8_width 64\n#define chip8_height 32\nstatic unsigned char chip8_bits[] = {\n"
+        ));
+        assert!(xbm.ends_with("0x00 };\n"));
+        let bytes: Vec<&str> = xbm
+            .lines()
+            .skip(3)
+            .flat_map(|line| line.split(','))
+            .map(|byte| byte.trim().trim_end_matches(" };"))
+            .filter(|byte| !byte.is_empty())
+            .collect();
+        assert_eq!(bytes.len(), 64 / 8 * 32);
+        // the leftmost pixel is stored in the least significant bit
+        assert_eq!(bytes[0], "0x0f");
+        assert_eq!(bytes[8], "0x09");
+        assert!(bytes[1..8]
+            .iter()
+            .chain(&bytes[9..])
+            .all(|&byte| byte == "0x00"));
+    }
+
+    #[test]
+    fn digest_is_reproducible() {
+        // RND V0, 0xFF; LD F, V0; DRW V0, V0, 5
+        let rom = vec![0xC0, 0xFF, 0xF0, 0x29, 0xD0, 0x05];
+        let first = Interpreter::new(rom.clone()).run_digest(3).unwrap();
+        let second = Interpreter::new(rom.clone()).run_digest(3).unwrap();
+        assert_eq!(first, second);
+        assert_ne!(first, Interpreter::new(rom).run_digest(2).unwrap());
+    }
+
+    #[test]
+    fn sprite_coordinates_wrap_around_screen() {
+        // LD V0, 200; LD F, V1; DRW V0, V0, 1
+        let mut interpreter = Interpreter::new(vec![0x60, 200, 0xF1, 0x29, 0xD0, 0x01]);
+        interpreter.run_headless_steps(3).unwrap();
+        let frame_buffer = interpreter.frame_buffer.read().unwrap();
+        // 200 % 64 = 8 and 200 % 32 = 8, only the top row of digit 0 is drawn
+        let mut expected = [0; Window::HEIGHT];
+        expected[8] = 0xF0 << (u128::BITS - 16);
+        assert_eq!(frame_buffer.rows(), expected);
+    }
+
+    #[test]
+    fn collision_reports_erased_rows() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter.set_log_collisions(true);
+        interpreter.address_register = FONT_START as u16;
+        // digit 0 at (6, 3)
+        assert_eq!(interpreter.draw_sprite(70, 3, 5), Ok(Vec::new()));
+        assert!(interpreter.frame_buffer.read().unwrap().is_set(6, 3));
+        // digit 1 overlaps the top row of digit 0 with its first row
+        interpreter.address_register = FONT_START as u16 + 5;
+        assert_eq!(interpreter.draw_sprite(6, 3, 1), Ok(vec![3]));
+        // and its bottom row with its second row only
+        assert_eq!(interpreter.draw_sprite(6, 6, 2), Ok(vec![7]));
+        assert_eq!(interpreter.registers[REG_VF], 1);
+    }
+
+    #[test]
+    fn odd_jump_target_depends_on_alignment() {
+        // JP 0x203
+        let mut interpreter = Interpreter::new(vec![0x12, 0x03]);
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.program_counter, 0x203);
+
+        let mut interpreter = Interpreter::new(vec![0x12, 0x03]);
+        interpreter.set_enforce_alignment(true);
+        assert_eq!(
+            interpreter.step(),
+            Err(Chip8Error::UnalignedJump {
+                instruction: Instruction::JpAddr(0x203),
+                address: 0x200,
+                target: 0x203,
+            })
+        );
+    }
+
+    #[test]
+    fn profile_csv_counts_families() {
+        // LD V0, 0x01; ADD V0, 0x01; LD V1, V0; CLS
+        let rom = vec![0x60, 0x01, 0x70, 0x01, 0x81, 0x00, 0x00, 0xE0];
+        let mut interpreter = Interpreter::new(rom.clone());
+        interpreter.run_headless_steps(4).unwrap();
+        assert_eq!(interpreter.profile_csv(), None);
+
+        let mut interpreter = Interpreter::new(rom);
+        interpreter.set_profiling(true);
+        interpreter.run_headless_steps(4).unwrap();
+        assert_eq!(
+            interpreter.profile_csv().unwrap(),
+            "opcode_family,count\nadd,1\ncls,1\nld,2\n"
+        );
+    }
+
+    #[test]
+    fn delay_timer_reads_current_value() {
+        // LD V0, 0x05; LD DT, V0; LD V1, DT; LD V2, DT; LD V3, DT
+        let mut interpreter = Interpreter::new(vec![
+            0x60, 0x05, 0xF0, 0x15, 0xF1, 0x07, 0xF2, 0x07, 0xF3, 0x07,
+        ]);
+        interpreter.run_headless_steps(3).unwrap();
+        // one frame passes between the reads
+        interpreter.tick_timers(1);
+        interpreter.step().unwrap();
+        interpreter.tick_timers(1);
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.registers[1..4], [5, 4, 3]);
+        assert_eq!(interpreter.timer_register, 3);
+    }
+
+    #[test]
+    fn state_snapshot_lists_registers_and_stack() {
+        // LD V1, 0x2A; LD I, 0x123; CALL 0x208; at 0x208: LD VF, 0x01
+        let mut interpreter = Interpreter::new(vec![
+            0x61, 0x2A, 0xA1, 0x23, 0x22, 0x08, 0x00, 0x00, 0x6F, 0x01,
+        ]);
+        interpreter.run_headless_steps(4).unwrap();
+        assert_eq!(
+            interpreter.state_snapshot(),
+            "PC=020A I=0123 SP=01 DT=00 ST=00\n\
+             V0=00 V1=2A V2=00 V3=00 V4=00 V5=00 V6=00 V7=00 \
+             V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=01\n\
+             stack=[0206]\n"
+        );
+    }
+
+    #[test]
+    fn drw_reads_vf_coordinate_before_setting_flag() {
+        // LD F, V0; LD VF, 0x08; DRW VF, VF, 5; LD VF, 0x08; DRW VF, VF, 5
+        let mut interpreter = Interpreter::new(vec![
+            0xF0, 0x29, 0x6F, 0x08, 0xDF, 0xF5, 0x6F, 0x08, 0xDF, 0xF5,
+        ]);
+        interpreter.run_headless_steps(3).unwrap();
+        {
+            let frame_buffer = interpreter.frame_buffer.read().unwrap();
+            assert!(frame_buffer.is_set(8, 8));
+            assert_eq!(frame_buffer.rows()[0], 0);
+        }
+        assert_eq!(interpreter.registers[REG_VF], 0);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.registers[REG_VF], 1);
+        assert_eq!(
+            *interpreter.frame_buffer.read().unwrap(),
+            FrameBuffer::default()
+        );
+    }
+
+    #[test]
+    fn empty_rom_has_no_instructions() {
+        assert!(!Interpreter::has_instructions(&[]));
+        assert!(!Interpreter::has_instructions(&[0x00; 16]));
+        // the first instruction after the end of the program is never reached
+        assert!(!Interpreter::has_instructions(&[0x00, 0x00, 0x60, 0x01]));
+        assert!(Interpreter::has_instructions(&[0x60, 0x01]));
+    }
+
+    #[test]
+    fn beep_follows_sound_register() {
+        // LD V0, 0x03; LD ST, V0; LD V1, 0x00; LD ST, V1
+        let mut interpreter =
+            Interpreter::new(vec![0x60, 0x03, 0xF0, 0x18, 0x61, 0x00, 0xF1, 0x18]);
+        interpreter.run_headless_steps(2).unwrap();
+        assert!(interpreter.sound_playing);
+        interpreter.tick_timers(2);
+        assert!(interpreter.sound_playing);
+        interpreter.tick_timers(1);
+        assert!(!interpreter.sound_playing);
+
+        // setting the sound register to 0 does not start the beep
+        interpreter.run_headless_steps(2).unwrap();
+        assert!(!interpreter.sound_playing);
+    }
+
+    #[test]
+    fn chunked_budget_matches_single_run() {
+        // RND V0, 0x3F; LD F, V0; DRW V0, V1, 5; ADD V1, 0x03; JP 0x200
+        let rom = vec![0xC0, 0x3F, 0xF0, 0x29, 0xD0, 0x15, 0x71, 0x03, 0x12, 0x00];
+        let build = || {
+            let mut interpreter = Interpreter::new(rom.clone());
+            interpreter.set_seed(42);
+            interpreter
+        };
+        let mut chunked = build();
+        for _ in 0..10 {
+            assert_eq!(chunked.run_budget(10), Ok(RunStatus::BudgetExhausted));
+        }
+        let mut single = build();
+        assert_eq!(single.run_budget(100), Ok(RunStatus::BudgetExhausted));
+        assert!(chunked.state_eq(&single));
+        assert_eq!(chunked.cycle_count, 100);
+    }
+
+    #[test]
+    fn break_on_draw_stops_after_each_sprite() {
+        // LD V0, 0x01; LD F, V0; DRW V0, V0, 1; CLS; DRW V0, V0, 1
+        let rom = vec![0x60, 0x01, 0xF0, 0x29, 0xD0, 0x01, 0x00, 0xE0, 0xD0, 0x01];
+        let mut interpreter = Interpreter::new(rom.clone());
+        let breaks: Vec<bool> = (0..5)
+            .map(|_| interpreter.step().unwrap().unwrap().break_on_draw)
+            .collect();
+        assert_eq!(breaks, [false; 5], "disabled by default");
+
+        let mut interpreter = Interpreter::new(rom.clone());
+        interpreter.set_break_on_draw(true);
+        let breaks: Vec<bool> = (0..5)
+            .map(|_| interpreter.step().unwrap().unwrap().break_on_draw)
+            .collect();
+        assert_eq!(breaks, [false, false, true, false, true]);
+
+        let mut interpreter = Interpreter::new(rom);
+        interpreter.set_break_on_draw(true);
+        assert_eq!(interpreter.run_budget(10), Ok(RunStatus::BreakOnDraw));
+        assert_eq!(interpreter.program_counter(), PROGRAM_START as u16 + 6);
+        // the top row of the glyph "1" is drawn
+        assert!(interpreter.frame_buffer().is_set(3, 1));
+        assert_eq!(interpreter.run_budget(10), Ok(RunStatus::BreakOnDraw));
+        assert_eq!(interpreter.run_budget(10), Ok(RunStatus::Halted));
+    }
+
+    #[test]
+    fn run_budget_stops_at_timeout() {
+        // JP 0x202; JP 0x200
+        let mut interpreter = Interpreter::new(vec![0x12, 0x02, 0x12, 0x00]);
+        interpreter.set_timeout(Some(Duration::ZERO));
+        assert_eq!(interpreter.run_budget(u64::MAX), Ok(RunStatus::TimedOut));
+    }
+
+    #[test]
+    fn quirk_presets_change_instructions() {
+        // LD V1, 0x01; LD V2, 0x04; SHR V1, V2
+        let rom = vec![0x61, 0x01, 0x62, 0x04, 0x81, 0x26];
+        let vip = run_with_quirks(Quirks::COSMAC_VIP, rom.clone(), 3);
+        assert_eq!((vip.registers[1], vip.registers[REG_VF]), (0x02, 0));
+        let schip = run_with_quirks(Quirks::SUPER_CHIP, rom, 3);
+        assert_eq!((schip.registers[1], schip.registers[REG_VF]), (0x00, 1));
+
+        // LD I, 0x300; LD [I], V2
+        let rom = vec![0xA3, 0x00, 0xF2, 0x55];
+        let vip = run_with_quirks(Quirks::COSMAC_VIP, rom.clone(), 2);
+        assert_eq!(vip.address_register, 0x303);
+        let schip = run_with_quirks(Quirks::SUPER_CHIP, rom, 2);
+        assert_eq!(schip.address_register, 0x300);
+
+        // LD V0, 0x02; LD V2, 0x04; JP V0, 0x220
+        let rom = vec![0x60, 0x02, 0x62, 0x04, 0xB2, 0x20];
+        let vip = run_with_quirks(Quirks::COSMAC_VIP, rom.clone(), 3);
+        assert_eq!(vip.program_counter, 0x222);
+        let schip = run_with_quirks(Quirks::SUPER_CHIP, rom, 3);
+        assert_eq!(schip.program_counter, 0x224);
+    }
+
+    #[test]
+    fn bcd_writes_hundreds_tens_and_ones() {
+        for (value, digits) in [
+            (0, [0, 0, 0]),
+            (7, [0, 0, 7]),
+            (99, [0, 9, 9]),
+            (255, [2, 5, 5]),
+        ] {
+            // LD V1, value; LD I, 0x300; LD B, V1
+            let mut interpreter = Interpreter::new(vec![0x61, value, 0xA3, 0x00, 0xF1, 0x33]);
+            interpreter.load_bytes(0x300, &[0xAA; 3]);
+            interpreter.run_headless_steps(3).unwrap();
+            assert_eq!(interpreter.memory[0x300..0x303], digits, "{value}");
+        }
+    }
+
+    #[test]
+    fn add_i_past_address_space_depends_on_mask() {
+        for (mask_address_register, expected) in [(true, 0x001), (false, 0x1001)] {
+            let quirks = Quirks {
+                mask_address_register,
+                ..Quirks::default()
+            };
+            // LD I, 0xFFF; LD V1, 0x02; ADD I, V1
+            let interpreter = run_with_quirks(quirks, vec![0xAF, 0xFF, 0x61, 0x02, 0xF1, 0x1E], 3);
+            assert_eq!(interpreter.address_register, expected);
+        }
+    }
+
+    #[test]
+    fn collision_in_top_row_survives_later_rows() {
+        // LD I, 0x300; DRW V0, V0, 1; LD I, 0x301; DRW V0, V0, 3
+        let rom = vec![0xA3, 0x00, 0xD0, 0x01, 0xA3, 0x01, 0xD0, 0x03];
+        let mut interpreter = Interpreter::new(rom.clone());
+        interpreter.load_bytes(0x300, &[0x80, 0x80, 0x40, 0x20]);
+        interpreter.run_headless_steps(4).unwrap();
+        assert_eq!(interpreter.registers[REG_VF], 1);
+        let mut expected = [0; Window::HEIGHT];
+        expected[1] = 1 << (u128::BITS - 2);
+        expected[2] = 1 << (u128::BITS - 3);
+        assert_eq!(interpreter.frame_buffer.read().unwrap().rows(), expected);
+
+        // the same sprite without an overlap
+        let mut interpreter = Interpreter::new(rom);
+        interpreter.load_bytes(0x300, &[0x01, 0x80, 0x40, 0x20]);
+        interpreter.run_headless_steps(4).unwrap();
+        assert_eq!(interpreter.registers[REG_VF], 0);
+    }
+
+    #[test]
+    fn state_eq_compares_state_but_not_configuration() {
+        // LD V1, 0x05; LD F, V1; DRW V1, V1, 5; CALL 0x208; ADD V1, 0x01
+        let rom = vec![0x61, 0x05, 0xF1, 0x29, 0xD1, 0x15, 0x22, 0x08, 0x71, 0x01];
+        let mut first = Interpreter::new(rom.clone());
+        let mut second = Interpreter::with_quirks(rom, Quirks::SUPER_CHIP);
+        assert!(first.state_eq(&second));
+        first.run_headless_steps(4).unwrap();
+        assert!(!first.state_eq(&second));
+        second.run_headless_steps(4).unwrap();
+        assert!(first.state_eq(&second));
+        first.run_headless_steps(1).unwrap();
+        assert!(!first.state_eq(&second));
+    }
+
+    #[test]
+    fn clock_rate_limits_instructions_per_frame() {
+        let mut interpreter = Interpreter::new(Vec::new());
+        interpreter.set_clock_hz(Some(600));
+        assert!(!interpreter.frame_budget_used(9, Duration::ZERO, 0));
+        assert!(interpreter.frame_budget_used(10, Duration::ZERO, 0));
+        // uneven rates round up to whole instructions
+        interpreter.set_clock_hz(Some(700));
+        assert!(!interpreter.frame_budget_used(11, Duration::ZERO, 0));
+        assert!(interpreter.frame_budget_used(12, Duration::ZERO, 0));
+
+        // without a clock rate, the frame ends after 1/60 s
+        interpreter.set_clock_hz(None);
+        assert!(!interpreter.frame_budget_used(1000, Duration::from_millis(10), 0));
+        assert!(interpreter.frame_budget_used(1, Duration::from_millis(17), 0));
+    }
+
+    #[test]
+    fn step_executes_one_instruction() {
+        // LD V3, 0x2A; LD F, V3; DRW V0, V0, 5
+        let mut interpreter = Interpreter::new(vec![0x63, 0x2A, 0xF3, 0x29, 0xD0, 0x05]);
+        let outcome = interpreter.step().unwrap().unwrap();
+        assert_eq!(outcome.instruction, Instruction::LdVxByte(3, 0x2A));
+        assert_eq!(interpreter.registers()[3], 0x2A);
+        assert_eq!(interpreter.program_counter(), PROGRAM_START as u16 + 2);
+        assert_eq!(interpreter.frame_buffer(), FrameBuffer::default());
+
+        interpreter.step().unwrap();
+        interpreter.step().unwrap();
+        assert!(interpreter.frame_buffer().is_set(0, 0));
+        // the zeroed memory past the ROM ends the program
+        assert_eq!(interpreter.step(), Ok(None));
+    }
+
+    #[test]
+    fn step_outcome_reports_effects() {
+        // CLS; LD V0, 0x01; DRW V0, V0, 1; LD V1, K
+        let mut interpreter =
+            Interpreter::new(vec![0x00, 0xE0, 0x60, 0x01, 0xD0, 0x01, 0xF1, 0x0A]);
+        let drew: Vec<bool> = (0..3)
+            .map(|_| interpreter.step().unwrap().unwrap().drew)
+            .collect();
+        assert_eq!(drew, [true, false, true]);
+
+        let outcome = interpreter.step().unwrap().unwrap();
+        assert!(outcome.waited_for_key);
+        assert!(!outcome.drew && !outcome.sound_changed);
+    }
+
+    #[test]
+    fn sound_indicator_toggles_only_on_change() {
+        // LD V0, 0x02; LD ST, V0; LD ST, V0
+        let mut interpreter = Interpreter::new(vec![0x60, 0x02, 0xF0, 0x18, 0xF0, 0x18]);
+        interpreter.step().unwrap();
+        assert!(interpreter.step().unwrap().unwrap().sound_changed);
+        assert!(interpreter.sound_playing);
+        assert!(!interpreter.step().unwrap().unwrap().sound_changed);
+        interpreter.tick_timers(2);
+        assert!(!interpreter.sound_playing);
+    }
+
+    #[test]
+    fn builder_applies_options() {
+        // LD V1, [I]; RND V2, 0xFF
+        let rom = vec![0xF1, 0x65, 0xC2, 0xFF];
+        let build = || {
+            Interpreter::builder(rom.clone())
+                .start_address(0x600)
+                .memory(0x000, vec![0x12, 0x34])
+                .seed(7)
+                .build()
+                .unwrap()
+        };
+        let mut interpreter = build();
+        assert_eq!(interpreter.program_counter(), 0x600);
+        assert_eq!(interpreter.memory()[0x600..][..4], rom);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.registers()[..2], [0x12, 0x34]);
+        // the same seed produces the same random numbers
+        let mut other = build();
+        other.run_headless_steps(2).unwrap();
+        assert_eq!(other.registers()[2], interpreter.registers()[2]);
+
+        // the initial memory survives a reset
+        interpreter.reset();
+        assert_eq!(interpreter.memory()[..2], [0x12, 0x34]);
+    }
+
+    #[test]
+    fn builder_rejects_addresses_outside_of_memory() {
+        let start = Interpreter::builder(vec![0x00, 0xE0])
+            .start_address(RAM_SIZE as u16)
+            .build();
+        assert_eq!(
+            start.err(),
+            Some(Chip8Error::OutOfBounds {
+                address: RAM_SIZE,
+                len: 2
+            })
+        );
+        let memory = Interpreter::builder(Vec::new())
+            .memory(RAM_SIZE - 1, vec![0x00, 0x00])
+            .build();
+        assert_eq!(
+            memory.err(),
+            Some(Chip8Error::OutOfBounds {
+                address: RAM_SIZE - 1,
+                len: 2
+            })
+        );
+    }
+
+    #[test]
+    fn rpl_flags_survive_soft_reset() {
+        // LD V0, 0x2A; LD V1, 0x07; LD R, V1; halt; LD V1, R
+        let mut interpreter = Interpreter::new(vec![
+            0x60, 0x2A, 0x61, 0x07, 0xF1, 0x75, 0x00, 0x00, 0xF1, 0x85,
+        ]);
+        interpreter.run_headless_steps(3).unwrap();
+        interpreter.handle_host_event(HostEvent::SoftReset).unwrap();
+        assert_eq!(interpreter.registers(), &[0; 16]);
+        assert_eq!(interpreter.program_counter(), PROGRAM_START as u16);
+        assert_eq!(interpreter.rpl_flags, [0x2A, 0x07, 0, 0, 0, 0, 0, 0]);
+        interpreter.program_counter = 0x208;
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.registers()[..2], [0x2A, 0x07]);
+
+        // a full reset clears the flags
+        interpreter.reset();
+        assert_eq!(interpreter.rpl_flags, [0; 8]);
+    }
+
+    #[test]
+    fn rpl_flags_round_trip() {
+        // LD V0, 0x2A; LD V1, 0x07; LD R, V1
+        let mut interpreter = Interpreter::new(vec![0x60, 0x2A, 0x61, 0x07, 0xF1, 0x75]);
+        interpreter.run_headless_steps(3).unwrap();
+        let mut saved = Vec::new();
+        interpreter.save_rpl_flags(&mut saved).unwrap();
+        assert_eq!(saved, [0x2A, 0x07, 0, 0, 0, 0, 0, 0]);
+
+        // LD V1, R
+        let mut interpreter = Interpreter::new(vec![0xF1, 0x85]);
+        interpreter.load_rpl_flags(&mut saved.as_slice()).unwrap();
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.registers()[..2], [0x2A, 0x07]);
+
+        // truncated files are rejected, without touching the flags
+        let err = interpreter
+            .load_rpl_flags(&mut [0xFF; 3].as_slice())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(interpreter.rpl_flags, [0x2A, 0x07, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn super_chip_draws_and_scrolls_large_sprites() {
+        // HIGH; LD I, 0x300; DRW V0, V0, 0; SCR; SCD 2; SCL; LOW
+        let rom = vec![
+            0x00, 0xFF, 0xA3, 0x00, 0xD0, 0x00, 0x00, 0xFB, 0x00, 0xC2, 0x00, 0xFC, 0x00, 0xFE,
+        ];
+        let mut sprite = vec![0; 32];
+        sprite[..2].copy_from_slice(&[0x80, 0x01]);
+        sprite[30] = 0x80;
+        let mut interpreter = Interpreter::builder(rom)
+            .memory(0x300, sprite)
+            .build()
+            .unwrap();
+        interpreter.run_headless_steps(3).unwrap();
+        let lit = |interpreter: &Interpreter| -> Vec<(usize, usize)> {
+            let frame_buffer = interpreter.frame_buffer();
+            (0..frame_buffer.height())
+                .flat_map(|y| (0..frame_buffer.width()).map(move |x| (x, y)))
+                .filter(|&(x, y)| frame_buffer.is_set(x, y))
+                .collect()
+        };
+        assert_eq!(interpreter.frame_buffer().width(), FrameBuffer::HIRES_WIDTH);
+        assert_eq!(lit(&interpreter), [(0, 0), (15, 0), (0, 15)]);
+        interpreter.run_headless_steps(1).unwrap();
+        assert_eq!(lit(&interpreter), [(4, 0), (19, 0), (4, 15)]);
+        interpreter.run_headless_steps(1).unwrap();
+        assert_eq!(lit(&interpreter), [(4, 2), (19, 2), (4, 17)]);
+        interpreter.run_headless_steps(1).unwrap();
+        assert_eq!(lit(&interpreter), [(0, 2), (15, 2), (0, 17)]);
+        // switching the resolution clears the screen
+        interpreter.run_headless_steps(1).unwrap();
+        assert_eq!(interpreter.frame_buffer().width(), Window::WIDTH);
+        assert_eq!(lit(&interpreter), []);
+    }
+
+    #[test]
+    fn chip48_quirks() {
+        // LD V1, 0x04; LD V2, 0x10; SHR V1, V2; LD I, 0x300; LD [I], V2; LD V0, 0x00; JP V0, 0x220
+        let rom = vec![
+            0x61, 0x04, 0x62, 0x10, 0x81, 0x26, 0xA3, 0x00, 0xF2, 0x55, 0x60, 0x00, 0xB2, 0x20,
+        ];
+        let run = |quirks| {
+            let interpreter = run_with_quirks(quirks, rom.clone(), 7);
+            (
+                interpreter.registers()[1],
+                interpreter.address_register(),
+                interpreter.program_counter(),
+            )
+        };
+        // shifts Vx in place, advances I to the last stored register and jumps with V2
+        assert_eq!(run(Quirks::CHIP_48), (0x02, 0x302, 0x230));
+        assert_eq!(run(Quirks::COSMAC_VIP), (0x08, 0x303, 0x220));
+    }
+
+    #[test]
+    fn two_page_display_skips_setup_and_clears_screen() {
+        // JP 0x260, with the program at 0x2C0:
+        // LD V0, 0x3C; LD F, V0; DRW V0, V0, 5; SYS 0x230
+        let mut rom = vec![0; 0xC8];
+        rom[..2].copy_from_slice(&[0x12, 0x60]);
+        rom[0xC0..].copy_from_slice(&[0x60, 0x3C, 0xF0, 0x29, 0xD0, 0x05, 0x02, 0x30]);
+        let mut interpreter = Interpreter::builder(rom)
+            .two_page_display(true)
+            .build()
+            .unwrap();
+        interpreter.run_headless_steps(1).unwrap();
+        assert_eq!(interpreter.program_counter(), 0x2C0);
+        interpreter.run_headless_steps(3).unwrap();
+        let frame_buffer = interpreter.frame_buffer();
+        assert_eq!(
+            (frame_buffer.width(), frame_buffer.height()),
+            (Window::WIDTH, FrameBuffer::HIRES_HEIGHT)
+        );
+        assert!(frame_buffer.is_set(60, 63));
+        interpreter.run_headless_steps(1).unwrap();
+        let frame_buffer = interpreter.frame_buffer();
+        assert_eq!(frame_buffer.resolution(), Resolution::TwoPage);
+        assert!(frame_buffer.rows().iter().all(|&row| row == 0));
+    }
+
+    #[test]
+    fn load_rom_rejects_rom_past_end_of_memory() {
+        let mut interpreter = Interpreter::builder(vec![0x00, 0xE0])
+            .start_address(ETI_660_START_ADDRESS)
+            .build()
+            .unwrap();
+        let available = RAM_SIZE - ETI_660_START_ADDRESS as usize;
+        assert_eq!(
+            interpreter.load_rom(vec![0; available + 1]),
+            Err(Chip8Error::RomTooLarge {
+                size: available + 1,
+                available,
+            })
+        );
+        assert_eq!(
+            interpreter.memory()[ETI_660_START_ADDRESS as usize + 1],
+            0xE0
+        );
+        interpreter.load_rom(vec![0xFF; available]).unwrap();
+        assert_eq!(interpreter.memory()[RAM_SIZE - 1], 0xFF);
+    }
+
+    #[test]
+    fn sys_runs_machine_code_with_vip_native() {
+        // SYS 0x300, with the routine at 0x300:
+        // LDI 0x0E; PHI R6; LDI 0xF3; PLO R6; LDI 0x2A; STR R6; INC RA; SEP R4
+        let mut rom = vec![0; 0x10B];
+        rom[..2].copy_from_slice(&[0x03, 0x00]);
+        rom[0x100..].copy_from_slice(&[
+            0xF8, 0x0E, 0xB6, 0xF8, 0xF3, 0xA6, 0xF8, 0x2A, 0x56, 0x1A, 0xD4,
+        ]);
+        let mut interpreter = Interpreter::new(rom.clone());
+        interpreter.set_vip_native(true);
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.registers[3], 0x2A);
+        assert_eq!(interpreter.address_register, 0x001);
+        assert_eq!(interpreter.program_counter, 0x202);
+
+        // without a CPU core, the routine is called like a CHIP-8 subroutine
+        let mut interpreter = Interpreter::new(rom);
+        interpreter.step().unwrap();
+        assert_eq!(interpreter.program_counter, 0x300);
+    }
+
+    #[test]
+    fn stuck_machine_code_halts() {
+        // SYS 0x202, with the routine BR 0x02 at 0x202
+        let mut interpreter = Interpreter::new(vec![0x02, 0x02, 0x30, 0x02]);
+        interpreter.set_vip_native(true);
+        assert_eq!(
+            interpreter.step(),
+            Err(Chip8Error::NativeRoutineStuck { address: 0x202 })
+        );
+    }
+
+    #[test]
+    fn shift_source_depends_on_quirk() {
+        let shift = |opcode: u16, shift_uses_vy| {
+            let [high, low] = opcode.to_be_bytes();
+            let quirks = Quirks {
+                shift_uses_vy,
+                ..Quirks::default()
+            };
+            // LD V1, 0x81; LD V2, 0x42; <shift>
+            let interpreter = run_with_quirks(quirks, vec![0x61, 0x81, 0x62, 0x42, high, low], 3);
+            (interpreter.registers()[1], interpreter.registers()[REG_VF])
+        };
+        // SHR V1, V2
+        assert_eq!(shift(0x8126, true), (0x21, 0));
+        assert_eq!(shift(0x8126, false), (0x40, 1));
+        // SHL V1, V2
+        assert_eq!(shift(0x812E, true), (0x84, 0));
+        assert_eq!(shift(0x812E, false), (0x02, 1));
+    }
+
+    #[test]
+    fn load_store_increment_depends_on_quirk() {
+        // LD I, 0x300; LD [I], V2; LD V2, [I]
+        let rom = vec![0xA3, 0x00, 0xF2, 0x55, 0xF2, 0x65];
+        let address_after = |load_store_increments_i| {
+            let quirks = Quirks {
+                load_store_increments_i,
+                ..Quirks::default()
+            };
+            let mut interpreter = run_with_quirks(quirks, rom.clone(), 2);
+            let stored = interpreter.address_register();
+            interpreter.run_headless_steps(1).unwrap();
+            (stored, interpreter.address_register())
+        };
+        assert_eq!(address_after(true), (0x303, 0x306));
+        assert_eq!(address_after(false), (0x300, 0x300));
+    }
+
+    #[test]
+    fn logic_ops_reset_vf_with_quirk() {
+        for (opcode, result) in [(0x8121u16, 0x0E), (0x8122, 0x08), (0x8123, 0x06)] {
+            for (vf_reset, flag) in [(true, 0), (false, 0x55)] {
+                let [high, low] = opcode.to_be_bytes();
+                let quirks = Quirks {
+                    vf_reset,
+                    ..Quirks::default()
+                };
+                // LD V1, 0x0C; LD V2, 0x0A; LD VF, 0x55; <logic op>
+                let rom = vec![0x61, 0x0C, 0x62, 0x0A, 0x6F, 0x55, high, low];
+                let interpreter = run_with_quirks(quirks, rom, 4);
+                assert_eq!(
+                    (interpreter.registers()[1], interpreter.registers()[REG_VF]),
+                    (result, flag),
+                    "{opcode:04X}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn jump_with_offset_register_depends_on_quirk() {
+        // LD V0, 0x10; LD V3, 0x20; JP V0, 0x300
+        let rom = vec![0x60, 0x10, 0x63, 0x20, 0xB3, 0x00];
+        for (jump_with_vx, target) in [(true, 0x320), (false, 0x310)] {
+            let quirks = Quirks {
+                jump_with_vx,
+                ..Quirks::default()
+            };
+            let interpreter = run_with_quirks(quirks, rom.clone(), 3);
+            assert_eq!(interpreter.program_counter(), target);
+        }
+    }
+
+    #[test]
+    fn index_overflow_sets_vf_with_quirk() {
+        let run = |start: u8, index_overflow_flag| {
+            let quirks = Quirks {
+                index_overflow_flag,
+                ..Quirks::default()
+            };
+            // LD VF, 0x55; LD I, 0xFxx; LD V1, 0x02; ADD I, V1
+            let rom = vec![0x6F, 0x55, 0xAF, start, 0x61, 0x02, 0xF1, 0x1E];
+            run_with_quirks(quirks, rom, 4).registers()[REG_VF]
+        };
+        assert_eq!(run(0xFF, true), 1);
+        assert_eq!(run(0x00, true), 0);
+        assert_eq!(run(0xFF, false), 0x55);
+    }
+
+    #[test]
+    fn draws_end_frame_with_display_wait() {
+        let draw = Instruction::Drw(0, 0, 5);
+        let mut interpreter = Interpreter::with_quirks(
+            Vec::new(),
+            Quirks {
+                display_wait: true,
+                ..Quirks::default()
+            },
+        );
+        assert!(interpreter.waits_for_display(&draw));
+        assert!(!interpreter.waits_for_display(&Instruction::Cls));
+        interpreter.quirks.display_wait = false;
+        assert!(!interpreter.waits_for_display(&draw));
+        // every instruction is presented on its own frame
+        interpreter.set_redraw_every_instruction(true);
+        assert!(interpreter.waits_for_display(&Instruction::Cls));
+    }
+
+    #[test]
+    fn vip_timing_ends_frame_after_machine_cycles() {
+        // ADD V0, 0x01; JP 0x200
+        let mut interpreter = Interpreter::new(vec![0x70, 0x01, 0x12, 0x00]);
+        interpreter.set_vip_timing(true);
+        let frame_end = VIP_CYCLES_PER_FRAME;
+        let mut instructions = 0;
+        while !interpreter.frame_budget_used(instructions, Duration::ZERO, frame_end) {
+            interpreter.step().unwrap();
+            instructions += 1;
+        }
+        // the frame ends with the instruction that crosses the frame's cycle count
+        assert!(interpreter.machine_cycles >= frame_end);
+        assert!(interpreter.machine_cycles < frame_end + 23);
+        assert!(!interpreter.frame_budget_used(instructions, Duration::ZERO, frame_end + 23));
+    }
+
+    #[test]
+    fn collision_is_accumulated_over_wrapped_rows() {
+        let mut interpreter = Interpreter::with_quirks(
+            Vec::new(),
+            Quirks {
+                clip_sprites: false,
+                ..Quirks::default()
+            },
+        );
+        interpreter.load_bytes(0x300, &[0x80, 0x80, 0x80]);
+        interpreter.address_register = 0x300;
+        assert_eq!(interpreter.draw_sprite(0, 31, 1), Ok(Vec::new()));
+        // only the first row at the bottom collides, the wrapped rows at the top do not
+        assert_eq!(interpreter.draw_sprite(0, 31, 3), Ok(vec![31]));
+        assert_eq!(interpreter.registers()[REG_VF], 1);
+        assert!(interpreter.frame_buffer().is_set(0, 0));
+        assert!(interpreter.frame_buffer().is_set(0, 1));
+        assert!(!interpreter.frame_buffer().is_set(0, 31));
+    }
+
+    #[test]
+    fn key_wait_takes_tapped_key() {
+        // LD V0, 0x05; LD DT, V0; LD V1, K; LD V2, DT
+        let mut interpreter =
+            Interpreter::new(vec![0x60, 0x05, 0xF0, 0x15, 0xF1, 0x0A, 0xF2, 0x07]);
+        interpreter.run_headless_steps(2).unwrap();
+        interpreter.inject_key_tap(0x9, 1);
+        interpreter.tick_timers(1);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.registers()[1], 0x9);
+        assert_eq!(interpreter.registers()[2], 4);
+    }
+
+    #[test]
+    fn stack_holds_sixteen_nested_calls() {
+        // every instruction calls the next one
+        let rom: Vec<u8> = (1..=17u16)
+            .flat_map(|idx| (0x2000 | (PROGRAM_START as u16 + 2 * idx)).to_be_bytes())
+            .collect();
+        let mut interpreter = Interpreter::new(rom);
+        interpreter.run_headless_steps(16).unwrap();
+        assert_eq!(
+            interpreter.step(),
+            Err(Chip8Error::StackOverflow { address: 0x220 })
+        );
+    }
+
+    #[test]
+    fn ret_returns_after_call() {
+        // CALL 0x204; halt; RET
+        let mut interpreter = Interpreter::new(vec![0x22, 0x04, 0x00, 0x00, 0x00, 0xEE]);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.program_counter(), 0x202);
+        assert!(interpreter.state_snapshot().ends_with("stack=[]\n"));
+        assert_eq!(interpreter.step(), Ok(None), "the ROM ends after the call");
+    }
+
+    #[test]
+    fn wrap_memory_wraps_program_counter_and_address_register() {
+        // JP 0xFFE; at 0xFFE: LD V0, 0x02; at 0x000: LD I, 0xFFF; ADD I, V0
+        let mut interpreter = Interpreter::builder(vec![0x1F, 0xFE])
+            .memory(0xFFE, vec![0x60, 0x02])
+            .memory(0x000, vec![0xAF, 0xFF, 0xF0, 0x1E])
+            .build()
+            .unwrap();
+        interpreter.set_wrap_memory(true);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.program_counter(), 0x000);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.address_register(), 0x001);
+    }
+
+    #[test]
+    fn super_chip_opcode_follows_unknown_opcode_policy() {
+        // LD HF, V0; LD V1, 0x01
+        let rom = vec![0xF0, 0x30, 0x61, 0x01];
+        let mut interpreter = Interpreter::new(rom.clone());
+        interpreter.set_super_chip_instructions(false);
+        assert!(matches!(
+            interpreter.step(),
+            Err(Chip8Error::InvalidOpcode { opcode: 0xF030, .. })
+        ));
+
+        let mut interpreter = Interpreter::new(rom);
+        interpreter.set_super_chip_instructions(false);
+        interpreter.set_unknown_opcode_policy(UnknownOpcodePolicy::Ignore);
+        interpreter.run_headless_steps(2).unwrap();
+        assert_eq!(interpreter.registers()[1], 0x01);
+        assert_eq!(interpreter.address_register(), 0);
+    }
+
+    #[test]
+    fn self_jump_and_exit_halt() {
+        // LD V0, 0x01; JP 0x202
+        let mut interpreter = Interpreter::new(vec![0x60, 0x01, 0x12, 0x02]);
+        assert_eq!(interpreter.run_budget(10), Ok(RunStatus::Halted));
+        assert!(interpreter.is_halted());
+        assert_eq!(interpreter.program_counter(), PROGRAM_START as u16 + 2);
+
+        // a jump to another address keeps running
+        // JP 0x202; JP 0x200
+        let mut interpreter = Interpreter::new(vec![0x12, 0x02, 0x12, 0x00]);
+        assert_eq!(interpreter.run_budget(10), Ok(RunStatus::BudgetExhausted));
+        assert!(!interpreter.is_halted());
+
+        // EXIT; LD V0, 0x01
+        let mut interpreter = Interpreter::new(vec![0x00, 0xFD, 0x60, 0x01]);
+        assert_eq!(interpreter.step(), Ok(None));
+        assert!(interpreter.is_halted());
+        assert_eq!(interpreter.registers()[0], 0);
+    }
 }
@@ -0,0 +1,32 @@
+//! Core of a CHIP-8 interpreter, mostly following
+//! [Cowgod's Reference](https://web.archive.org/web/20231223002150/https://devernay.free.fr/hacks/chip8/C8TECH10.HTM).
+//!
+//! The [`Interpreter`] can either open an SDL window with [`Interpreter::execute`], or be driven
+//! headlessly, e.g. instruction by instruction with [`Interpreter::step`]. The window requires the
+//! default `sdl` feature; without it, the crate does not link against SDL2 and only runs headless:
+//!
+//! ```
+//! use chip8_emulator::{Instruction, Interpreter};
+//!
+//! // LD V0, 0x2A
+//! let mut interpreter = Interpreter::new(vec![0x60, 0x2A]);
+//! let outcome = interpreter.step().unwrap().unwrap();
+//! assert_eq!(outcome.instruction, Instruction::LdVxByte(0, 0x2A));
+//! assert_eq!(interpreter.registers()[0], 0x2A);
+//! ```
+
+pub mod cdp1802;
+pub mod error;
+pub mod extensions;
+pub mod instruction;
+pub mod interpreter;
+pub mod platform;
+pub mod quirks;
+pub mod window;
+
+pub use error::Chip8Error;
+pub use instruction::Instruction;
+pub use interpreter::{Interpreter, InterpreterBuilder, StepOutcome};
+pub use platform::Platform;
+pub use quirks::Quirks;
+pub use window::{FrameBuffer, Resolution};
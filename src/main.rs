@@ -1,27 +1,605 @@
-mod instruction;
-mod interpreter;
-mod window;
+#[cfg(feature = "url")]
+mod download;
 
-use std::{fs, process::ExitCode};
+use std::{fmt::Write, fs, io, panic, process::ExitCode, time::Duration};
 
-use crate::interpreter::Interpreter;
+use chip8_emulator::{
+    extensions, interpreter, Chip8Error, Instruction, Interpreter, Platform, Quirks,
+};
+
+/// Exit code used when the ROM file could not be read.
+const EXIT_READ_FAILURE: u8 = 2;
+
+/// Exit code used when the ROM contains an invalid or disallowed instruction.
+const EXIT_INVALID_INSTRUCTION: u8 = 3;
+
+/// Exit code used when the window thread crashed.
+const EXIT_WINDOW_CRASHED: u8 = 4;
+
+/// Exit code used when the ROM jumps to an invalid address.
+const EXIT_INVALID_JUMP: u8 = 5;
+
+/// Exit code used when the ROM misuses the stack, e.g. by returning without a subroutine call or
+/// nesting too many calls.
+const EXIT_STACK_ERROR: u8 = 6;
+
+/// Exit code used when the ROM accesses memory past the end of the interpreter memory.
+const EXIT_OUT_OF_BOUNDS: u8 = 7;
+
+/// Returns the exit code matching the kind of error that stopped the ROM.
+fn exit_code(err: &Chip8Error) -> u8 {
+    match err {
+        Chip8Error::InvalidOpcode { .. }
+        | Chip8Error::DisallowedInstruction { .. }
+        | Chip8Error::NativeRoutineStuck { .. }
+        | Chip8Error::Extension { .. } => EXIT_INVALID_INSTRUCTION,
+        Chip8Error::StackOverflow { .. } | Chip8Error::StackUnderflow { .. } => EXIT_STACK_ERROR,
+        Chip8Error::UnalignedJump { .. } | Chip8Error::ReservedJump { .. } => EXIT_INVALID_JUMP,
+        Chip8Error::OutOfBounds { .. } => EXIT_OUT_OF_BOUNDS,
+        Chip8Error::RomTooLarge { .. }
+        | Chip8Error::EmptyRom
+        | Chip8Error::InvalidHexDigit(_)
+        | Chip8Error::OddHexDigitCount(_) => EXIT_READ_FAILURE,
+        Chip8Error::WindowCrashed(_) => EXIT_WINDOW_CRASHED,
+    }
+}
+
+/// Writes the final screen as ASCII art, if the ROM stopped without an error.
+fn write_screen_on_halt(
+    out: &mut impl io::Write,
+    interpreter: &Interpreter,
+    result: &Result<(), Chip8Error>,
+) {
+    if result.is_ok() {
+        let _ = out.write_all(interpreter.display_ascii().as_bytes());
+    }
+}
 
 fn main() -> ExitCode {
-    let Some(rom_path) = std::env::args().nth(1) else {
+    let args: Vec<String> = std::env::args().collect();
+    run(&args)
+}
+
+/// Runs the emulator with the given command line arguments, including the program name.
+fn run(args: &[String]) -> ExitCode {
+    let Some(rom_path) = args.get(1) else {
         eprintln!("Invalid file path");
         return ExitCode::FAILURE;
     };
-    let rom_file = fs::read(rom_path).unwrap();
+    let has_flag = |flag: &str| args.iter().skip(2).any(|arg| arg == flag);
+    let option_value = |option: &str| {
+        args.windows(2)
+            .skip(2)
+            .find(|pair| pair[0] == option)
+            .map(|pair| pair[1].as_str())
+    };
+    let (rom_source, rom_file) = if rom_path == "--url" {
+        let Some(url) = args.get(2) else {
+            eprintln!("Usage: --url <http-url>");
+            return ExitCode::FAILURE;
+        };
+        (url, download_rom(url))
+    } else {
+        (rom_path, fs::read(rom_path).map_err(|err| err.to_string()))
+    };
+    let rom_file = match rom_file {
+        Ok(rom_file) => rom_file,
+        Err(err) => {
+            eprintln!("Failed to read ROM {rom_source}: {err}");
+            return ExitCode::from(EXIT_READ_FAILURE);
+        }
+    };
 
-    let mut interpreter = Interpreter::new(rom_file);
-    interpreter.execute().expect("Failed to run ROM");
+    if !Interpreter::has_instructions(&rom_file) {
+        eprintln!(
+            "Warning: ROM {rom_source} contains no instructions before its first 0000 word, nothing will be executed"
+        );
+    }
+    let platform = match option_value("--platform") {
+        Some(name) => {
+            let Some(platform) = Platform::from_name(name) else {
+                eprintln!(
+                    "Unknown platform {name}, expected one of {}",
+                    Platform::NAMES.join(", ")
+                );
+                return ExitCode::FAILURE;
+            };
+            Some(platform)
+        }
+        None => None,
+    };
+    let mut extensions = extensions::rom_uses_extensions(&rom_file);
+    if platform.is_none_or(|platform| platform.super_chip_instructions()) {
+        extensions = extensions.unsupported();
+    }
+    if !extensions.is_empty() {
+        eprintln!("Warning: ROM appears to use unsupported extensions: {extensions}");
+    }
 
-    if std::env::args()
-        .nth(2)
-        .is_some_and(|arg| arg == "--dump-memory")
+    let mut quirks = match option_value("--quirks") {
+        Some(preset) => {
+            let Some(quirks) = Quirks::from_preset(preset) else {
+                eprintln!("Unknown quirks preset {preset}, expected vip, chip48, schip or xochip");
+                return ExitCode::FAILURE;
+            };
+            quirks
+        }
+        None => platform
+            .map(|platform| platform.quirks())
+            .unwrap_or_default(),
+    };
+    if has_flag("--mask-i") {
+        quirks.mask_address_register = true;
+    }
+    if let Some(overrides) = option_value("--quirk") {
+        if let Err(quirk) = apply_quirk_overrides(&mut quirks, overrides) {
+            eprintln!(
+                "Invalid quirk {quirk}, expected <name>=<on|off> with one of {}",
+                Quirks::NAMES.join(", ")
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+    let start_address = match option_value("--start-address") {
+        Some(address) => {
+            let Some(address) = parse_number(address).and_then(|address| address.try_into().ok())
+            else {
+                eprintln!("Invalid start address {address}");
+                return ExitCode::FAILURE;
+            };
+            address
+        }
+        None if has_flag("--eti660") => interpreter::ETI_660_START_ADDRESS,
+        None => interpreter::PROGRAM_START as u16,
+    };
+    let mut interpreter = match Interpreter::builder(rom_file)
+        .quirks(quirks)
+        .start_address(start_address)
+        .two_page_display(has_flag("--two-page-hires"))
+        .build()
     {
-        interpreter.dump_memory();
+        Ok(interpreter) => interpreter,
+        Err(err) => {
+            eprintln!("Failed to load ROM {rom_source}: {err}");
+            return ExitCode::from(EXIT_READ_FAILURE);
+        }
+    };
+    if let Some(idx) = args.iter().position(|arg| arg == "--show-sprite") {
+        let (Some(address), Some(height)) = (
+            args.get(idx + 1).and_then(|arg| parse_number(arg)),
+            args.get(idx + 2).and_then(|arg| parse_number(arg)),
+        ) else {
+            eprintln!("Usage: --show-sprite <addr> <height>");
+            return ExitCode::FAILURE;
+        };
+        print!("{}", interpreter.sprite_ascii(address, height));
+        return ExitCode::SUCCESS;
+    }
+
+    if let Some(platform) = platform {
+        interpreter.set_super_chip_instructions(platform.super_chip_instructions());
+    }
+    interpreter.set_enforce_alignment(has_flag("--enforce-alignment"));
+    interpreter.set_log_collisions(has_flag("--log-collisions"));
+    interpreter.set_loop_on_halt(has_flag("--loop"));
+    interpreter.set_profiling(option_value("--profile-csv").is_some());
+    interpreter.set_rainbow(has_flag("--rainbow"));
+    interpreter.set_redraw_every_instruction(has_flag("--redraw-every-instruction"));
+    interpreter.set_skip_unchanged_frames(has_flag("--skip-unchanged-frames"));
+    interpreter.set_show_status(has_flag("--status"));
+    interpreter.set_strict_decode(has_flag("--strict-decode"));
+    interpreter.set_vip_native(has_flag("--vip-native"));
+    interpreter.set_vip_timing(has_flag("--vip-timing"));
+    interpreter.set_visual_sound(has_flag("--visual-sound"));
+    interpreter.set_warn_uninitialized(has_flag("--warn-uninit"));
+    interpreter.set_wrap_memory(has_flag("--wrap-memory"));
+    if let Some(policy) = option_value("--unknown-opcodes") {
+        let Some(policy) = interpreter::UnknownOpcodePolicy::from_name(policy) else {
+            eprintln!("Unknown opcode policy {policy}, expected halt, warn or ignore");
+            return ExitCode::FAILURE;
+        };
+        interpreter.set_unknown_opcode_policy(policy);
+    }
+    if let Some(audio_rate) = option_value("--audio-rate") {
+        let Some(audio_rate) = audio_rate.parse().ok().filter(|&rate| rate > 0) else {
+            eprintln!("Invalid audio sample rate {audio_rate}");
+            return ExitCode::FAILURE;
+        };
+        interpreter.set_audio_rate(audio_rate);
+    }
+    if let Some(clock_hz) = option_value("--clock") {
+        let Ok(clock_hz) = clock_hz.parse() else {
+            eprintln!("Invalid clock rate {clock_hz}");
+            return ExitCode::FAILURE;
+        };
+        // 0 disables the throttling
+        interpreter.set_clock_hz(Some(clock_hz).filter(|&hz| hz > 0));
+    }
+    if let Some(timeout) = option_value("--timeout") {
+        let Some(timeout) = timeout
+            .parse()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        else {
+            eprintln!("Invalid timeout {timeout}");
+            return ExitCode::FAILURE;
+        };
+        interpreter.set_timeout(Some(timeout));
+    }
+    if let Some(tap) = option_value("--tap") {
+        let Some((key, cycles)) = tap
+            .split_once(':')
+            .and_then(|(key, cycles)| Some((parse_number(key)?, cycles.parse().ok()?)))
+            .filter(|&(key, _)| key <= 0xF)
+        else {
+            eprintln!("Invalid key tap {tap}, expected <key>:<cycles>");
+            return ExitCode::FAILURE;
+        };
+        interpreter.inject_key_tap(key as u8, cycles);
+    }
+    if let Some(frames) = option_value("--min-beep") {
+        let Ok(frames) = frames.parse() else {
+            eprintln!("Invalid minimum beep duration {frames}");
+            return ExitCode::FAILURE;
+        };
+        interpreter.set_min_beep_frames(frames);
+    }
+    if let Some(allowed) = option_value("--allow") {
+        let mnemonics: Vec<String> = allowed
+            .split(',')
+            .map(|mnemonic| mnemonic.trim().to_lowercase())
+            .collect();
+        if let Some(unknown) = mnemonics
+            .iter()
+            .find(|mnemonic| !Instruction::MNEMONICS.contains(&mnemonic.as_str()))
+        {
+            eprintln!("Unknown instruction family {unknown}");
+            return ExitCode::FAILURE;
+        }
+        interpreter.set_allowed_mnemonics(Some(mnemonics));
+    }
+    if let Some(seed) = option_value("--seed") {
+        let Some(seed) = parse_number(seed) else {
+            eprintln!("Invalid seed {seed}");
+            return ExitCode::FAILURE;
+        };
+        interpreter.set_seed(seed as u64);
+    }
+    if let Some(max_cycles) = option_value("--digest") {
+        let Ok(max_cycles) = max_cycles.parse() else {
+            eprintln!("Invalid number of cycles {max_cycles}");
+            return ExitCode::FAILURE;
+        };
+        return match interpreter.run_digest(max_cycles) {
+            Ok(digest) => {
+                println!(
+                    "{}",
+                    digest.iter().fold(String::new(), |mut output, byte| {
+                        let _ = write!(output, "{byte:02x}");
+                        output
+                    })
+                );
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("Failed to run ROM: {err}");
+                ExitCode::from(exit_code(&err))
+            }
+        };
+    }
+    if let Some(path) = option_value("--rpl-file") {
+        load_rpl_file(&mut interpreter, path);
+    }
+    let debug = has_flag("--debug");
+    let result = match (option_value("--capture-frames"), option_value("--steps")) {
+        (Some(_), Some(_)) => {
+            eprintln!("--steps and --capture-frames cannot be combined");
+            return ExitCode::FAILURE;
+        }
+        (None, Some(steps)) => {
+            let Ok(steps) = steps.parse() else {
+                eprintln!("Invalid number of steps {steps}");
+                return ExitCode::FAILURE;
+            };
+            run_guarded(&mut interpreter, debug, |interpreter| {
+                interpreter.run_headless_steps(steps)
+            })
+        }
+        (Some(frames), None) => {
+            let Ok(frames) = frames.parse() else {
+                eprintln!("Invalid number of frames {frames}");
+                return ExitCode::FAILURE;
+            };
+            run_guarded(&mut interpreter, debug, |interpreter| {
+                interpreter.run_headless_with_frames(frames, |frame_buffer| {
+                    println!("{}", interpreter::frame_ascii(frame_buffer));
+                })
+            })
+        }
+        (None, None) => run_guarded(&mut interpreter, debug, Interpreter::execute),
+    };
+
+    if has_flag("--print-screen-on-halt") {
+        write_screen_on_halt(&mut std::io::stdout(), &interpreter, &result);
+    }
+    if let Some(path) = option_value("--export-xbm") {
+        if let Err(err) = fs::write(path, interpreter.export_xbm()) {
+            eprintln!("Failed to export screen to {path}: {err}");
+        }
+    }
+    if let (Some(path), Some(csv)) = (option_value("--profile-csv"), interpreter.profile_csv()) {
+        if let Err(err) = fs::write(path, csv) {
+            eprintln!("Failed to export profile to {path}: {err}");
+        }
+    }
+    if let Some(path) = option_value("--rpl-file") {
+        if let Err(err) =
+            fs::File::create(path).and_then(|mut file| interpreter.save_rpl_flags(&mut file))
+        {
+            eprintln!("Failed to save RPL flags to {path}: {err}");
+        }
+    }
+
+    let mut dump_out: Box<dyn io::Write> = if has_flag("--dump-stdout") {
+        Box::new(io::stdout())
+    } else {
+        Box::new(io::stderr())
+    };
+    if has_flag("--dump-registers") {
+        if let Err(err) = interpreter.dump_registers(&mut dump_out) {
+            eprintln!("Failed to dump registers: {err}");
+        }
+    }
+    if has_flag("--dump-memory") {
+        if let Err(err) = interpreter.dump_memory(&mut dump_out) {
+            eprintln!("Failed to dump memory: {err}");
+        }
+    }
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Failed to run ROM: {err}");
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
+
+/// Restores the RPL user flags saved in the file at `path`.
+///
+/// A missing file is expected on the first run, a corrupt one is reported and the flags stay
+/// cleared; either way the ROM still runs.
+fn load_rpl_file(interpreter: &mut Interpreter, path: &str) {
+    match fs::File::open(path) {
+        Ok(mut file) => {
+            if let Err(err) = interpreter.load_rpl_flags(&mut file) {
+                eprintln!("Ignoring RPL flags in {path}: {err}");
+            }
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => eprintln!("Failed to read RPL flags from {path}: {err}"),
+    }
+}
+
+/// Runs the interpreter with `run`, dumping its state to stderr if it panics and `debug` is set.
+///
+/// The panic is resumed afterwards, so it still aborts the emulator.
+fn run_guarded(
+    interpreter: &mut Interpreter,
+    debug: bool,
+    run: impl FnOnce(&mut Interpreter) -> Result<(), Chip8Error>,
+) -> Result<(), Chip8Error> {
+    match panic::catch_unwind(panic::AssertUnwindSafe(|| run(interpreter))) {
+        Ok(result) => result,
+        Err(payload) => {
+            if debug {
+                eprint!("Interpreter state:\n{}", interpreter.state_snapshot());
+            }
+            panic::resume_unwind(payload)
+        }
+    }
+}
+
+/// Downloads the ROM at `url`.
+#[cfg(feature = "url")]
+fn download_rom(url: &str) -> Result<Vec<u8>, String> {
+    download::download_rom(url)
+}
+
+/// Fails, as downloading ROMs requires the `url` feature.
+#[cfg(not(feature = "url"))]
+fn download_rom(_url: &str) -> Result<Vec<u8>, String> {
+    Err("Loading ROMs by URL requires building with `--features url`".to_string())
+}
+
+/// Applies the comma separated `<name>=<on|off>` quirk `overrides`.
+///
+/// Returns the first invalid override, if any.
+fn apply_quirk_overrides<'a>(quirks: &mut Quirks, overrides: &'a str) -> Result<(), &'a str> {
+    for quirk in overrides.split(',') {
+        let valid = match quirk.trim().split_once('=') {
+            Some((name, "on")) => quirks.set(name, true),
+            Some((name, "off")) => quirks.set(name, false),
+            _ => false,
+        };
+        if !valid {
+            return Err(quirk);
+        }
+    }
+    Ok(())
+}
+
+/// Parses a decimal or a `0x` prefixed hexadecimal number.
+fn parse_number(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_opcode_has_its_own_exit_code() {
+        let err = Chip8Error::InvalidOpcode {
+            opcode: 0xFFFF,
+            expected: None,
+        };
+        assert_eq!(exit_code(&err), EXIT_INVALID_INSTRUCTION);
+        assert_ne!(exit_code(&err), EXIT_READ_FAILURE);
+    }
+
+    #[test]
+    fn stack_errors_share_exit_code() {
+        for err in [
+            Chip8Error::StackOverflow { address: 0x200 },
+            Chip8Error::StackUnderflow { address: 0x200 },
+        ] {
+            assert_eq!(exit_code(&err), EXIT_STACK_ERROR);
+        }
+    }
+
+    #[test]
+    fn window_crash_has_its_own_exit_code() {
+        let err = Chip8Error::WindowCrashed("boom".to_string());
+        assert_eq!(exit_code(&err), EXIT_WINDOW_CRASHED);
+    }
+
+    #[test]
+    fn screen_is_only_printed_on_clean_halt() {
+        let interpreter = Interpreter::new(Vec::new());
+        let mut out = Vec::new();
+        write_screen_on_halt(&mut out, &interpreter, &Ok(()));
+        let screen = String::from_utf8(out).unwrap();
+        assert_eq!(screen.lines().count(), 32);
+        assert!(screen.lines().all(|line| line == ".".repeat(64)));
+
+        let mut out = Vec::new();
+        let err = Chip8Error::InvalidOpcode {
+            opcode: 0xFFFF,
+            expected: None,
+        };
+        write_screen_on_halt(&mut out, &interpreter, &Err(err));
+        assert!(out.is_empty());
+    }
+
+    /// Writes `rom` to a temporary file and runs the emulator on it with the given options.
+    fn run_rom(name: &str, rom: &[u8], options: &[&str]) -> ExitCode {
+        let path = std::env::temp_dir().join(format!("chip8-{}-{name}.ch8", std::process::id()));
+        fs::write(&path, rom).unwrap();
+        let args: Vec<String> = ["chip8", path.to_str().unwrap()]
+            .iter()
+            .chain(options)
+            .map(|arg| arg.to_string())
+            .collect();
+        let exit_code = run(&args);
+        fs::remove_file(path).unwrap();
+        exit_code
+    }
+
+    #[test]
+    fn invalid_opcode_rom_exits_with_its_exit_code() {
+        // LD V0, 0x01; invalid
+        let rom = [0x60, 0x01, 0xFF, 0xFF];
+        assert_eq!(
+            run_rom("invalid", &rom, &["--steps", "2"]),
+            ExitCode::from(EXIT_INVALID_INSTRUCTION)
+        );
+        assert_eq!(
+            run_rom("valid", &rom[..2], &["--steps", "2"]),
+            ExitCode::SUCCESS
+        );
     }
 
-    ExitCode::SUCCESS
+    #[test]
+    fn ret_without_call_exits_with_stack_error() {
+        // RET
+        assert_eq!(
+            run_rom("ret", &[0x00, 0xEE], &["--steps", "1"]),
+            ExitCode::from(EXIT_STACK_ERROR)
+        );
+    }
+
+    #[test]
+    fn sprite_past_memory_end_exits_with_out_of_bounds() {
+        // LD I, 0xFFF; DRW V0, V0, 2
+        let rom = [0xAF, 0xFF, 0xD0, 0x02];
+        assert_eq!(
+            run_rom("out_of_bounds", &rom, &["--steps", "2"]),
+            ExitCode::from(EXIT_OUT_OF_BOUNDS)
+        );
+        assert_eq!(
+            run_rom("wrapped", &rom, &["--steps", "2", "--wrap-memory"]),
+            ExitCode::SUCCESS
+        );
+    }
+
+    #[test]
+    fn rpl_file_is_created_and_replaces_corrupt_flags() {
+        let path = std::env::temp_dir().join(format!("chip8-{}-rpl.bin", std::process::id()));
+        let path_arg = path.to_str().unwrap();
+        // LD V0, 0x2A; LD R, V0
+        let rom = [0x60, 0x2A, 0xF0, 0x75];
+        for existing in [None, Some(&b"corrupt"[..])] {
+            match existing {
+                Some(bytes) => fs::write(&path, bytes).unwrap(),
+                None => {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+            assert_eq!(
+                run_rom("rpl", &rom, &["--steps", "2", "--rpl-file", path_arg]),
+                ExitCode::SUCCESS
+            );
+            assert_eq!(fs::read(&path).unwrap(), [0x2A, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn steps_and_capture_frames_are_rejected_together() {
+        assert_eq!(
+            run_rom(
+                "combined",
+                &[0x60, 0x01],
+                &["--steps", "1", "--capture-frames", "1"]
+            ),
+            ExitCode::FAILURE
+        );
+    }
+
+    #[test]
+    fn quirk_overrides_toggle_named_quirks() {
+        let mut quirks = Quirks::COSMAC_VIP;
+        assert_eq!(
+            apply_quirk_overrides(&mut quirks, "vf_reset=off, jump_with_vx=on"),
+            Ok(())
+        );
+        assert_eq!(
+            quirks,
+            Quirks {
+                vf_reset: false,
+                jump_with_vx: true,
+                ..Quirks::COSMAC_VIP
+            }
+        );
+        assert_eq!(
+            apply_quirk_overrides(&mut quirks, "clip_sprites=off,wrap=on"),
+            Err("wrap=on")
+        );
+        assert_eq!(
+            apply_quirk_overrides(&mut quirks, "vf_reset"),
+            Err("vf_reset")
+        );
+    }
+
+    #[test]
+    fn start_address_accepts_decimal_and_hex() {
+        assert_eq!(parse_number("0x600"), Some(0x600));
+        assert_eq!(parse_number("1536"), Some(0x600));
+        assert_eq!(parse_number("0x"), None);
+        assert_eq!(parse_number("600h"), None);
+    }
 }
@@ -0,0 +1,78 @@
+use crate::quirks::Quirks;
+
+/// CHIP-8 variants, each bundling the quirks and instructions of its original interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// CHIP-8 on the COSMAC VIP.
+    Chip8,
+    /// CHIP-48 on the HP-48 calculators.
+    Chip48,
+    /// SUPER-CHIP 1.1 on the HP-48 calculators.
+    SuperChip,
+    /// XO-CHIP, as implemented by Octo.
+    ///
+    /// Only the SUPER-CHIP subset of its instructions is supported.
+    XoChip,
+}
+
+impl Platform {
+    /// Names of all platforms, see [`Self::from_name`].
+    pub const NAMES: [&'static str; 4] = ["chip8", "chip48", "schip", "xochip"];
+
+    /// Returns the platform with the given `name`, one of [`Self::NAMES`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "chip8" => Some(Self::Chip8),
+            "chip48" => Some(Self::Chip48),
+            "schip" => Some(Self::SuperChip),
+            "xochip" => Some(Self::XoChip),
+            _ => None,
+        }
+    }
+
+    /// Quirks of the platform's interpreter.
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks::COSMAC_VIP,
+            Self::Chip48 => Quirks::CHIP_48,
+            Self::SuperChip => Quirks::SUPER_CHIP,
+            Self::XoChip => Quirks::XO_CHIP,
+        }
+    }
+
+    /// Checks if the platform supports the SUPER-CHIP instructions, i.e. scrolling, the high
+    /// resolution mode, 16x16 sprites and the large font.
+    pub fn super_chip_instructions(&self) -> bool {
+        matches!(self, Self::SuperChip | Self::XoChip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_name_maps_to_a_platform() {
+        let platforms = Platform::NAMES.map(|name| Platform::from_name(name).unwrap());
+        assert_eq!(
+            platforms,
+            [
+                Platform::Chip8,
+                Platform::Chip48,
+                Platform::SuperChip,
+                Platform::XoChip
+            ]
+        );
+        assert_eq!(Platform::from_name("megachip"), None);
+    }
+
+    #[test]
+    fn platforms_bundle_quirks_and_instructions() {
+        assert_eq!(Platform::Chip8.quirks(), Quirks::COSMAC_VIP);
+        assert_eq!(Platform::XoChip.quirks(), Quirks::XO_CHIP);
+        assert!(!Platform::Chip8.super_chip_instructions());
+        assert!(!Platform::Chip48.super_chip_instructions());
+        assert!(Platform::SuperChip.super_chip_instructions());
+        assert!(Platform::XoChip.super_chip_instructions());
+    }
+}
@@ -0,0 +1,235 @@
+/// Behavior of instructions, which differs between CHIP-8 implementations.
+///
+/// The default follows the original COSMAC VIP interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8xy6`/`8xyE` shift Vy and store the result in Vx, instead of shifting Vx in place.
+    pub shift_uses_vy: bool,
+    /// `Fx55`/`Fx65` advance I past the last stored/loaded register, instead of leaving it
+    /// untouched.
+    pub load_store_increments_i: bool,
+    /// `Fx55`/`Fx65` advance I by `x` instead of `x + 1`, leaving it at the last register, like
+    /// the CHIP-48. Only applies if [`Self::load_store_increments_i`] is set.
+    pub load_store_increments_by_x: bool,
+    /// `Bnnn` jumps to `nnn + Vx`, where `x` is the highest nibble of `nnn`, instead of `nnn + V0`.
+    pub jump_with_vx: bool,
+    /// `Fx1E` keeps I within the 12-bit address space (`0x000` to `0xFFF`), instead of allowing
+    /// the full 16 bits.
+    pub mask_address_register: bool,
+    /// `Fx1E` sets VF to 1 if I exceeds `0xFFF` and to 0 otherwise, like the Amiga interpreter.
+    pub index_overflow_flag: bool,
+    /// `8xy1`/`8xy2`/`8xy3` reset VF to 0, as a side effect of the VIP implementation.
+    pub vf_reset: bool,
+    /// `Dxyn` waits for the vertical blank interrupt, limiting draws to one per 60 Hz frame.
+    pub display_wait: bool,
+    /// `Dxyn` clips sprites at the edges of the screen, instead of wrapping them around.
+    pub clip_sprites: bool,
+}
+
+impl Quirks {
+    /// Quirks of the original COSMAC VIP interpreter.
+    pub const COSMAC_VIP: Self = Self {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        load_store_increments_by_x: false,
+        jump_with_vx: false,
+        mask_address_register: false,
+        index_overflow_flag: false,
+        vf_reset: true,
+        display_wait: true,
+        clip_sprites: true,
+    };
+
+    /// Quirks of the CHIP-48 interpreter for the HP-48 calculators, which several classic games
+    /// were written for.
+    pub const CHIP_48: Self = Self {
+        shift_uses_vy: false,
+        load_store_increments_i: true,
+        load_store_increments_by_x: true,
+        jump_with_vx: true,
+        mask_address_register: false,
+        index_overflow_flag: false,
+        vf_reset: false,
+        display_wait: false,
+        clip_sprites: true,
+    };
+
+    /// Quirks of the SUPER-CHIP interpreter, which most modern ROMs are written for.
+    pub const SUPER_CHIP: Self = Self {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        load_store_increments_by_x: false,
+        jump_with_vx: true,
+        mask_address_register: false,
+        index_overflow_flag: false,
+        vf_reset: false,
+        display_wait: false,
+        clip_sprites: true,
+    };
+
+    /// Quirks of Octo's XO-CHIP, which follows the VIP except for VF reset and sprite clipping.
+    pub const XO_CHIP: Self = Self {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        load_store_increments_by_x: false,
+        jump_with_vx: false,
+        mask_address_register: false,
+        index_overflow_flag: false,
+        vf_reset: false,
+        display_wait: false,
+        clip_sprites: false,
+    };
+
+    /// Returns the quirks of the preset with the given `name`, either `vip`, `chip48`, `schip` or
+    /// `xochip`.
+    pub fn from_preset(name: &str) -> Option<Self> {
+        match name {
+            "vip" => Some(Self::COSMAC_VIP),
+            "chip48" => Some(Self::CHIP_48),
+            "schip" => Some(Self::SUPER_CHIP),
+            "xochip" => Some(Self::XO_CHIP),
+            _ => None,
+        }
+    }
+
+    /// Names of the individual quirks, see [`Self::set`].
+    pub const NAMES: [&'static str; 9] = [
+        "shift_uses_vy",
+        "load_store_increments_i",
+        "load_store_increments_by_x",
+        "jump_with_vx",
+        "mask_address_register",
+        "index_overflow_flag",
+        "vf_reset",
+        "display_wait",
+        "clip_sprites",
+    ];
+
+    /// Enables or disables the quirk with the given `name`, named like the fields.
+    ///
+    /// Returns `false` if there is no such quirk.
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let quirk = match name {
+            "shift_uses_vy" => &mut self.shift_uses_vy,
+            "load_store_increments_i" => &mut self.load_store_increments_i,
+            "load_store_increments_by_x" => &mut self.load_store_increments_by_x,
+            "jump_with_vx" => &mut self.jump_with_vx,
+            "mask_address_register" => &mut self.mask_address_register,
+            "index_overflow_flag" => &mut self.index_overflow_flag,
+            "vf_reset" => &mut self.vf_reset,
+            "display_wait" => &mut self.display_wait,
+            "clip_sprites" => &mut self.clip_sprites,
+            _ => return false,
+        };
+        *quirk = enabled;
+        true
+    }
+
+    /// Register added to the address of a `Bnnn` jump to `addr`.
+    pub fn jump_register(&self, addr: u16) -> u8 {
+        if self.jump_with_vx {
+            (addr >> 8) as u8 & 0xF
+        } else {
+            0
+        }
+    }
+
+    /// Amount I is advanced by after storing or loading the registers `V0` through `Vx`.
+    pub fn load_store_increment(&self, reg_x: u8) -> u16 {
+        match (
+            self.load_store_increments_i,
+            self.load_store_increments_by_x,
+        ) {
+            (false, _) => 0,
+            (true, false) => reg_x as u16 + 1,
+            (true, true) => reg_x as u16,
+        }
+    }
+
+    /// Register shifted by a `8xy6`/`8xyE` shift.
+    pub fn shift_register(&self, reg_x: u8, reg_y: u8) -> u8 {
+        if self.shift_uses_vy {
+            reg_y
+        } else {
+            reg_x
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::COSMAC_VIP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shift_register() {
+        let quirks = Quirks {
+            shift_uses_vy: true,
+            ..Quirks::default()
+        };
+        assert_eq!(quirks.shift_register(1, 2), 2);
+        let quirks = Quirks {
+            shift_uses_vy: false,
+            ..Quirks::default()
+        };
+        assert_eq!(quirks.shift_register(1, 2), 1);
+    }
+
+    #[test]
+    fn load_store_increment() {
+        let mut quirks = Quirks {
+            load_store_increments_i: false,
+            load_store_increments_by_x: false,
+            ..Quirks::default()
+        };
+        assert_eq!(quirks.load_store_increment(3), 0);
+        // advancing by x has no effect without the increment
+        quirks.load_store_increments_by_x = true;
+        assert_eq!(quirks.load_store_increment(3), 0);
+        quirks.load_store_increments_i = true;
+        assert_eq!(quirks.load_store_increment(3), 3);
+        quirks.load_store_increments_by_x = false;
+        assert_eq!(quirks.load_store_increment(3), 4);
+    }
+
+    #[test]
+    fn jump_register() {
+        let quirks = Quirks {
+            jump_with_vx: true,
+            ..Quirks::default()
+        };
+        assert_eq!(quirks.jump_register(0x234), 2);
+        let quirks = Quirks {
+            jump_with_vx: false,
+            ..Quirks::default()
+        };
+        assert_eq!(quirks.jump_register(0x234), 0);
+    }
+
+    #[test]
+    fn set_each_quirk() {
+        for name in Quirks::NAMES {
+            let mut quirks = Quirks::default();
+            assert!(quirks.set(name, true), "{name}");
+            let enabled = quirks;
+            assert!(quirks.set(name, false), "{name}");
+            assert_ne!(quirks, enabled, "{name} is not toggled");
+        }
+        assert!(!Quirks::default().set("unknown", true));
+    }
+
+    #[test]
+    fn presets() {
+        assert_eq!(Quirks::from_preset("vip"), Some(Quirks::COSMAC_VIP));
+        assert_eq!(Quirks::from_preset("chip48"), Some(Quirks::CHIP_48));
+        assert_eq!(Quirks::from_preset("schip"), Some(Quirks::SUPER_CHIP));
+        assert_eq!(Quirks::from_preset("xochip"), Some(Quirks::XO_CHIP));
+        assert_eq!(Quirks::from_preset("amiga"), None);
+        assert_eq!(Quirks::default(), Quirks::COSMAC_VIP);
+    }
+}
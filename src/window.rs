@@ -1,8 +1,12 @@
+use std::sync::{mpsc::TryRecvError, Arc, RwLock};
+#[cfg(feature = "sdl")]
 use std::{
-    sync::{Arc, RwLock},
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{Receiver, Sender},
     time::Duration,
 };
 
+#[cfg(feature = "sdl")]
 use sdl2::{
     audio::{AudioCallback, AudioSpecDesired},
     event::Event,
@@ -15,12 +19,25 @@ use sdl2::{
 /// Beep sound.
 ///
 /// This should be played when the sound register is non-zero.
+#[cfg(feature = "sdl")]
 struct Beep {
     phase_inc: f32,
     phase: f32,
     volume: f32,
 }
 
+#[cfg(feature = "sdl")]
+impl Beep {
+    /// Frequency of the beep in Hz.
+    const FREQUENCY: f32 = 440.0;
+
+    /// Phase increment per sample to produce a beep of [`Self::FREQUENCY`] at `sample_rate`.
+    fn phase_increment(sample_rate: i32) -> f32 {
+        Self::FREQUENCY / sample_rate as f32
+    }
+}
+
+#[cfg(feature = "sdl")]
 impl AudioCallback for Beep {
     type Channel = f32;
 
@@ -46,41 +63,320 @@ enum WindowCommand {
     ControlSound(bool),
 }
 
+/// Events triggered by host keys, which are sent to the interpreter.
+#[derive(Debug, PartialEq, Eq)]
+pub enum HostEvent {
+    /// Load the hex bytes contained in the clipboard as a new ROM.
+    PasteRom(String),
+    /// Reset the interpreter, keeping the RPL user flags.
+    SoftReset,
+    /// Execute more instructions per second.
+    SpeedUp,
+    /// Execute fewer instructions per second.
+    SlowDown,
+    /// The window thread panicked with the given message and is no longer running.
+    Crashed(String),
+}
+
+/// Resolutions of the screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// 64x32 pixels of the original CHIP-8.
+    #[default]
+    Low,
+    /// 64x64 pixels of the two-page hires CHIP-8 variant.
+    TwoPage,
+    /// 128x64 pixels of the SUPER-CHIP high resolution mode.
+    High,
+}
+
+/// Pixels of the screen, one `u128` per row with the leftmost pixel in the most significant bit.
+///
+/// Resolutions with a width of 64 pixels only use the upper 64 bits of each row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameBuffer {
+    rows: [u128; FrameBuffer::HIRES_HEIGHT],
+    resolution: Resolution,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self {
+            rows: [0; Self::HIRES_HEIGHT],
+            resolution: Resolution::default(),
+        }
+    }
+}
+
+impl FrameBuffer {
+    /// Width of the screen in the high resolution mode.
+    pub const HIRES_WIDTH: usize = 128;
+
+    /// Height of the screen in the high resolution mode.
+    pub const HIRES_HEIGHT: usize = 64;
+
+    /// Width of the screen in the current resolution.
+    pub fn width(&self) -> usize {
+        match self.resolution {
+            Resolution::Low | Resolution::TwoPage => Window::WIDTH,
+            Resolution::High => Self::HIRES_WIDTH,
+        }
+    }
+
+    /// Height of the screen in the current resolution.
+    pub fn height(&self) -> usize {
+        match self.resolution {
+            Resolution::Low => Window::HEIGHT,
+            Resolution::TwoPage | Resolution::High => Self::HIRES_HEIGHT,
+        }
+    }
+
+    /// Current resolution of the screen.
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Switches to the given `resolution`, which clears the screen.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+        self.clear();
+    }
+
+    /// Rows of the screen in the current resolution.
+    pub fn rows(&self) -> &[u128] {
+        &self.rows[..self.height()]
+    }
+
+    /// Checks if the pixel at `x`, `y` is lit.
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        self.rows[y] & (1 << (u128::BITS as usize - 1 - x)) != 0
+    }
+
+    /// Turns off all pixels.
+    pub fn clear(&mut self) {
+        self.rows.fill(0);
+    }
+
+    /// XORs the `sprite` row, whose leftmost pixel is in the most significant bit, onto `row`
+    /// starting at column `x`.
+    ///
+    /// Pixels past the right edge are either clipped or wrap around to the left edge.
+    /// Returns whether any lit pixel was turned off.
+    pub fn xor_row(&mut self, row: usize, sprite: u128, x: usize, clip: bool) -> bool {
+        let shifted = if clip {
+            sprite >> x
+        } else if self.width() == Self::HIRES_WIDTH {
+            sprite.rotate_right(x as u32)
+        } else {
+            ((((sprite >> 64) as u64).rotate_right(x as u32)) as u128) << 64
+        } & self.row_mask();
+        let collision = self.rows[row] & shifted != 0;
+        self.rows[row] ^= shifted;
+        collision
+    }
+
+    /// Scrolls the screen down by `n` pixels.
+    pub fn scroll_down(&mut self, n: usize) {
+        let height = self.height();
+        self.rows
+            .copy_within(..height - n.min(height), n.min(height));
+        self.rows[..n.min(height)].fill(0);
+    }
+
+    /// Scrolls the screen right by `n` pixels.
+    pub fn scroll_right(&mut self, n: usize) {
+        let mask = self.row_mask();
+        self.rows
+            .iter_mut()
+            .for_each(|row| *row = (*row >> n) & mask);
+    }
+
+    /// Scrolls the screen left by `n` pixels.
+    pub fn scroll_left(&mut self, n: usize) {
+        self.rows.iter_mut().for_each(|row| *row <<= n);
+    }
+
+    /// Bitmask of the columns visible in the current resolution.
+    fn row_mask(&self) -> u128 {
+        u128::MAX << (u128::BITS as usize - self.width())
+    }
+}
+
+/// Mapping of host keys to the keys of the CHIP-8 keypad.
+///
+/// A key of the keypad can be pressed by several host keys. By default, the keys are mapped
+/// as follows:
+/// Keypad       Keyboard
+/// +-+-+-+-+    +-+-+-+-+
+/// |1|2|3|C|    |1|2|3|4|
+/// +-+-+-+-+    +-+-+-+-+
+/// |4|5|6|D|    |Q|W|E|R|
+/// +-+-+-+-+ => +-+-+-+-+
+/// |7|8|9|E|    |A|S|D|F|
+/// +-+-+-+-+    +-+-+-+-+
+/// |A|0|B|F|    |Z|X|C|V|
+/// +-+-+-+-+    +-+-+-+-+
+///
+/// Additionally, the arrow keys are mapped to the same keys as WASD.
+#[cfg(feature = "sdl")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    /// Host keys and the CHIP-8 key they press.
+    bindings: Vec<(Scancode, u8)>,
+}
+
+#[cfg(feature = "sdl")]
+impl Keymap {
+    /// Presses the CHIP-8 `key` with the host key `scancode`, replacing any previous binding of
+    /// the `scancode`.
+    pub fn bind(&mut self, scancode: Scancode, key: u8) {
+        assert!(key < 16, "Trying to bind invalid key {key}");
+        self.bindings.retain(|&(bound, _)| bound != scancode);
+        self.bindings.push((scancode, key));
+    }
+
+    /// Returns the CHIP-8 key pressed by the host key `scancode`, if any.
+    pub fn key(&self, scancode: Scancode) -> Option<u8> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == scancode)
+            .map(|&(_, key)| key)
+    }
+
+    /// Returns the host keys which press the CHIP-8 `key`.
+    pub fn scancodes(&self, key: u8) -> impl Iterator<Item = Scancode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound)| bound == key)
+            .map(|&(scancode, _)| scancode)
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: vec![
+                (Scancode::Num1, 0x1),
+                (Scancode::Num2, 0x2),
+                (Scancode::Num3, 0x3),
+                (Scancode::Num4, 0xC),
+                (Scancode::Q, 0x4),
+                (Scancode::W, 0x5),
+                (Scancode::E, 0x6),
+                (Scancode::R, 0xD),
+                (Scancode::A, 0x7),
+                (Scancode::S, 0x8),
+                (Scancode::D, 0x9),
+                (Scancode::F, 0xE),
+                (Scancode::Z, 0xA),
+                (Scancode::X, 0x0),
+                (Scancode::C, 0xB),
+                (Scancode::V, 0xF),
+            ],
+        };
+        keymap.bind(Scancode::Up, 0x5);
+        keymap.bind(Scancode::Left, 0x7);
+        keymap.bind(Scancode::Down, 0x8);
+        keymap.bind(Scancode::Right, 0x9);
+        keymap
+    }
+}
+
 #[derive(Debug)]
 pub struct Window {
     /// Fame Buffer of the current window.
-    ///
-    /// Since the interpreter only supports a max width of 64 pixel,
-    /// `u64`s are (mis-)used as bitfields.
-    frame_buffer: Arc<RwLock<[u64; Self::HEIGHT]>>,
+    frame_buffer: Arc<RwLock<FrameBuffer>>,
     sender: Option<std::sync::mpsc::Sender<WindowCommand>>,
     receiver: Option<std::sync::mpsc::Receiver<u8>>,
+    event_receiver: Option<std::sync::mpsc::Receiver<HostEvent>>,
+    /// Whether the window has been asked to report the next released key.
+    waiting_for_key: bool,
     thread: Option<std::thread::JoinHandle<()>>,
+    /// Options passed to the window thread when it is spawned.
+    options: WindowOptions,
+    /// Keymap passed to the window thread when it is spawned.
+    #[cfg(feature = "sdl")]
+    keymap: Keymap,
+}
+
+/// Options of the window, which are fixed once the window thread is spawned.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+struct WindowOptions {
+    /// Desired audio sample rate in Hz.
+    audio_rate: i32,
+    /// Whether an indicator is shown while the sound is playing.
+    visual_sound: bool,
+    /// Whether draw calls are skipped if the screen has not changed since it was last presented.
+    skip_unchanged_frames: bool,
+    /// Whether the foreground color slowly cycles through all hues.
+    rainbow: bool,
 }
 impl Window {
-    pub fn new(frame_buffer: Arc<RwLock<[u64; Self::HEIGHT]>>) -> Self {
+    pub fn new(frame_buffer: Arc<RwLock<FrameBuffer>>) -> Self {
         Self {
             frame_buffer,
             sender: None,
             receiver: None,
+            event_receiver: None,
+            waiting_for_key: false,
             thread: None,
+            options: WindowOptions {
+                audio_rate: Self::DEFAULT_AUDIO_RATE,
+                visual_sound: false,
+                skip_unchanged_frames: false,
+                rainbow: false,
+            },
+            #[cfg(feature = "sdl")]
+            keymap: Keymap::default(),
         }
     }
 
-    /// Width of the interpreter window.
-    pub const WIDTH: usize = 64;
+    /// Sets the desired audio sample rate in Hz.
+    ///
+    /// Only takes effect when the window is spawned.
+    pub fn set_audio_rate(&mut self, audio_rate: i32) {
+        self.options.audio_rate = audio_rate;
+    }
 
-    /// Height of the interpreter window.
-    pub const HEIGHT: usize = 32;
+    /// Enables or disables the visual sound indicator, which is shown while the sound is playing.
+    ///
+    /// Only takes effect when the window is spawned.
+    pub fn set_visual_sound(&mut self, enabled: bool) {
+        self.options.visual_sound = enabled;
+    }
 
-    /// Scale factor, which each pixel is scaled by.
-    const SCALE_FACTOR: usize = 10;
+    /// Enables or disables skipping draw calls, which would present the same screen again.
+    ///
+    /// Only takes effect when the window is spawned.
+    pub fn set_skip_unchanged_frames(&mut self, enabled: bool) {
+        self.options.skip_unchanged_frames = enabled;
+    }
 
-    /// Color of the background (non-lit pixels) of the window
-    const COLOR_BACKGROUND: Color = Color::RGB(28, 29, 30);
+    /// Sets the mapping of host keys to the CHIP-8 keypad.
+    ///
+    /// Only takes effect when the window is spawned.
+    #[cfg(feature = "sdl")]
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
 
-    /// Color of the foreground (lit pixels) of the window
-    const COLOR_FOREGROUND: Color = Color::RGB(182, 236, 170);
+    /// Enables or disables cycling the foreground color through all hues.
+    ///
+    /// Only takes effect when the window is spawned.
+    pub fn set_rainbow(&mut self, enabled: bool) {
+        self.options.rainbow = enabled;
+    }
+
+    /// Width of the interpreter window in low resolution pixels.
+    pub const WIDTH: usize = 64;
+
+    /// Height of the interpreter window in low resolution pixels.
+    pub const HEIGHT: usize = 32;
+
+    /// Default audio sample rate in Hz.
+    const DEFAULT_AUDIO_RATE: i32 = 44100;
 
     /// Digits that the interpreter can display.
     /// Ordered from 0 to F.
@@ -103,12 +399,36 @@ impl Window {
         [0xF0, 0x80, 0xF0, 0x80, 0x80],
     ];
 
+    /// Large 8x10 digits used by the SUPER-CHIP.
+    /// Ordered from 0 to F.
+    pub const BIG_DIGITS: [[u8; 10]; 16] = [
+        [0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF],
+        [0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF],
+        [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF],
+        [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+        [0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03],
+        [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+        [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF],
+        [0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18],
+        [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF],
+        [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF],
+        [0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3],
+        [0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC],
+        [0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C],
+        [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC],
+        [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF],
+        [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0],
+    ];
+
     /// Queues a call.
     /// This causes the window contents to be redrawn, based on the [`Self::frame_buffer`].
+    ///
+    /// Returns immediately if the window has not been spawned.
     pub fn queue_draw(&self) {
+        if self.sender.is_none() {
+            return;
+        }
         self.send_command(WindowCommand::Draw);
-        // due to waiting for an interrupt, the CHIP-8 is limited to 60 fps
-        std::thread::sleep(Duration::from_secs_f64(1.0 / 60.0));
     }
 
     /// Clears the current screen.
@@ -116,13 +436,18 @@ impl Window {
         self.send_command(WindowCommand::Clear);
         // reset the frame_buffer to 0
         let mut frame_buffer = self.frame_buffer.write().unwrap();
-        frame_buffer.fill(0);
+        frame_buffer.clear();
     }
 
     /// Checks if the given key is pressed.
+    ///
+    /// Always `false` if the window has not been spawned.
     pub fn is_key_pressed(&mut self, key: u8) -> bool {
+        let Some(receiver) = self.receiver.as_ref() else {
+            return false;
+        };
         self.send_command(WindowCommand::IsPressed(key));
-        match self.receiver.as_ref().unwrap().recv() {
+        match receiver.recv() {
             Ok(val) => val != 0,
             Err(_) => {
                 eprintln!("Failed to receive response");
@@ -131,14 +456,28 @@ impl Window {
         }
     }
 
-    /// Checks if the given key is pressed.
-    pub fn wait_for_key_press(&mut self) -> u8 {
-        self.send_command(WindowCommand::WaitKeyPress);
-        match self.receiver.as_ref().unwrap().recv() {
-            Ok(val) => val,
-            Err(_) => {
+    /// Returns the next key, which is released after the first call, without blocking.
+    ///
+    /// Returns `None` until a key has been pressed and released, like on the COSMAC VIP.
+    /// Returns key `0` immediately if the window has not been spawned.
+    pub fn poll_key_release(&mut self) -> Option<u8> {
+        if self.receiver.is_none() {
+            return Some(0);
+        }
+        if !self.waiting_for_key {
+            self.send_command(WindowCommand::WaitKeyPress);
+            self.waiting_for_key = true;
+        }
+        match self.receiver.as_ref()?.try_recv() {
+            Ok(key) => {
+                self.waiting_for_key = false;
+                Some(key)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
                 eprintln!("Failed to receive response");
-                0
+                self.waiting_for_key = false;
+                Some(0)
             }
         }
     }
@@ -150,6 +489,11 @@ impl Window {
         self.send_command(WindowCommand::ControlSound(playing))
     }
 
+    /// Returns the next pending [`HostEvent`], if any.
+    pub fn poll_event(&self) -> Option<HostEvent> {
+        self.event_receiver.as_ref()?.try_recv().ok()
+    }
+
     /// Checks if the window is still open
     pub fn is_open(&self) -> bool {
         self.thread
@@ -165,196 +509,458 @@ impl Window {
         let _ = sender.send(cmd);
     }
 
+    /// Checks if the `screen` (frame buffer and sound indicator) has to be presented, given the
+    /// screen which was `presented` last, if it is still shown.
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    fn needs_present(
+        options: WindowOptions,
+        presented: Option<&(FrameBuffer, bool)>,
+        screen: &(FrameBuffer, bool),
+    ) -> bool {
+        !options.skip_unchanged_frames || presented != Some(screen)
+    }
+
+    /// Opens the window, which is not possible without the `sdl` feature.
+    ///
+    /// The window stays closed, so [`Self::is_open`] returns `false`.
+    #[cfg(not(feature = "sdl"))]
+    pub fn spawn(&mut self) {
+        eprintln!("Warning: built without the `sdl` feature, the window cannot be opened");
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl Window {
+    /// Scale factor, which each low resolution pixel is scaled by.
+    const SCALE_FACTOR: usize = 10;
+
+    /// Color of the background (non-lit pixels) of the window
+    const COLOR_BACKGROUND: Color = Color::RGB(28, 29, 30);
+
+    /// Color of the foreground (lit pixels) of the window
+    const COLOR_FOREGROUND: Color = Color::RGB(182, 236, 170);
+
+    /// Time it takes to cycle the foreground color through all hues, if enabled.
+    const RAINBOW_PERIOD: Duration = Duration::from_secs(10);
+
+    /// Color of the indicator shown while the sound is playing
+    const COLOR_SOUND_INDICATOR: Color = Color::RGB(236, 99, 95);
+
+    /// Host key that loads the hex bytes in the clipboard as a new ROM.
+    const KEY_PASTE_ROM: Scancode = Scancode::F5;
+
+    /// Host key that resets the interpreter, keeping the RPL user flags.
+    const KEY_SOFT_RESET: Scancode = Scancode::F6;
+
+    /// Host keys that increase the clock rate, `+` on the main keyboard and on the keypad.
+    const KEYS_SPEED_UP: [Scancode; 2] = [Scancode::Equals, Scancode::KpPlus];
+
+    /// Host keys that decrease the clock rate, `-` on the main keyboard and on the keypad.
+    const KEYS_SLOW_DOWN: [Scancode; 2] = [Scancode::Minus, Scancode::KpMinus];
+
     pub fn spawn(&mut self) {
         let (tx, rx) = std::sync::mpsc::channel::<WindowCommand>();
         let (respond_tx, respond_rx) = std::sync::mpsc::channel::<u8>();
+        let (event_tx, event_rx) = std::sync::mpsc::channel::<HostEvent>();
         self.sender.replace(tx);
         self.receiver.replace(respond_rx);
+        self.event_receiver.replace(event_rx);
         let frame_buffer = Arc::clone(&self.frame_buffer);
+        let options = self.options;
+        let keymap = self.keymap.clone();
         self.thread.replace(std::thread::spawn(move || {
-            let sdl_context = sdl2::init().unwrap();
-            let video_subsystem = sdl_context.video().unwrap();
-            let audio_subsystem = sdl_context.audio().unwrap();
-
-            let audio = audio_subsystem
-                .open_playback(
-                    None,
-                    &(AudioSpecDesired {
-                        freq: Some(44100),
-                        channels: Some(1),
-                        samples: Some(4096),
-                    }),
-                    |spec| Beep {
-                        phase_inc: 440.0 / spec.freq as f32,
-                        phase: 0.0,
-                        volume: 0.25,
-                    },
-                )
-                .unwrap();
-
-            let window = video_subsystem
-                .window(
-                    "CHIP-8 Emulator",
-                    (Self::WIDTH * Self::SCALE_FACTOR) as u32,
-                    (Self::HEIGHT * Self::SCALE_FACTOR) as u32,
-                )
-                .position_centered()
-                .vulkan()
-                .build()
-                .unwrap();
-
-            let mut canvas = window
-                .into_canvas()
-                .build()
-                .map_err(|e| e.to_string())
-                .unwrap();
-            canvas.set_draw_color(Self::COLOR_BACKGROUND);
-            canvas.present();
-            let mut event_pump = sdl_context.event_pump().unwrap();
-
-            let mut wait_for_key = false;
-            loop {
-                match rx.recv_timeout(std::time::Duration::new(0, 1_000_000_000u32 / 30)) {
-                    Ok(WindowCommand::Draw) => Self::draw(&frame_buffer, &mut canvas),
-                    Ok(WindowCommand::Clear) => {
-                        canvas.set_draw_color(Self::COLOR_BACKGROUND);
-                        canvas.clear();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                Self::run(&frame_buffer, options, &keymap, &rx, &respond_tx, &event_tx)
+            }));
+            if let Err(panic) = result {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                let _ = event_tx.send(HostEvent::Crashed(message));
+            }
+        }));
+    }
+
+    /// Runs the window until it is closed or the interpreter quits.
+    fn run(
+        frame_buffer: &Arc<RwLock<FrameBuffer>>,
+        options: WindowOptions,
+        keymap: &Keymap,
+        rx: &Receiver<WindowCommand>,
+        respond_tx: &Sender<u8>,
+        event_tx: &Sender<HostEvent>,
+    ) {
+        let sdl_context = sdl2::init().unwrap();
+        let video_subsystem = sdl_context.video().unwrap();
+        let audio_subsystem = sdl_context.audio().unwrap();
+
+        let audio = audio_subsystem
+            .open_playback(
+                None,
+                &(AudioSpecDesired {
+                    freq: Some(options.audio_rate),
+                    channels: Some(1),
+                    samples: Some(4096),
+                }),
+                |spec| Beep {
+                    phase_inc: Beep::phase_increment(spec.freq),
+                    phase: 0.0,
+                    volume: 0.25,
+                },
+            )
+            .unwrap();
+
+        let window = video_subsystem
+            .window(
+                "CHIP-8 Emulator",
+                (Self::WIDTH * Self::SCALE_FACTOR) as u32,
+                (Self::HEIGHT * Self::SCALE_FACTOR) as u32,
+            )
+            .position_centered()
+            .vulkan()
+            .build()
+            .unwrap();
+
+        let mut canvas = window
+            .into_canvas()
+            .build()
+            .map_err(|e| e.to_string())
+            .unwrap();
+        canvas.set_draw_color(Self::COLOR_BACKGROUND);
+        canvas.present();
+        let mut event_pump = sdl_context.event_pump().unwrap();
+
+        let mut wait_for_key = false;
+        let mut sound_playing = false;
+        // screen and sound indicator of the last draw call, used to skip unchanged frames
+        let mut presented = None;
+        let started = std::time::Instant::now();
+        loop {
+            let sound_indicator = options.visual_sound && sound_playing;
+            let foreground = if options.rainbow {
+                let cycle = started.elapsed().as_secs_f32() / Self::RAINBOW_PERIOD.as_secs_f32();
+                Self::hue_to_rgb(cycle.fract() * 360.0)
+            } else {
+                Self::COLOR_FOREGROUND
+            };
+            match rx.recv_timeout(std::time::Duration::new(0, 1_000_000_000u32 / 30)) {
+                Ok(WindowCommand::Draw) => {
+                    let screen = (*frame_buffer.read().unwrap(), sound_indicator);
+                    if Self::needs_present(options, presented.as_ref(), &screen) {
+                        Self::draw(frame_buffer, &mut canvas, foreground, sound_indicator);
+                        presented = Some(screen);
                     }
-                    Ok(WindowCommand::IsPressed(key)) => {
-                        respond_tx
-                            .send(
-                                event_pump
-                                    .keyboard_state()
-                                    .is_scancode_pressed(Self::map_key(key))
-                                    as u8,
-                            )
-                            .expect("Failed to send keycode");
+                }
+                Ok(WindowCommand::Clear) => {
+                    canvas.set_draw_color(Self::COLOR_BACKGROUND);
+                    canvas.clear();
+                    presented = None;
+                }
+                Ok(WindowCommand::IsPressed(key)) => {
+                    let keyboard_state = event_pump.keyboard_state();
+                    let pressed = keymap
+                        .scancodes(key)
+                        .any(|scancode| keyboard_state.is_scancode_pressed(scancode));
+                    respond_tx
+                        .send(pressed as u8)
+                        .expect("Failed to send keycode");
+                }
+                Ok(WindowCommand::WaitKeyPress) => {
+                    wait_for_key = true;
+                }
+                Ok(WindowCommand::ControlSound(playing)) => {
+                    if playing {
+                        audio.resume();
+                    } else {
+                        audio.pause();
+                    }
+                    // update the indicator right away, instead of waiting for the next draw call
+                    if options.visual_sound && playing != sound_playing {
+                        Self::draw(frame_buffer, &mut canvas, foreground, playing);
+                        presented = None;
                     }
-                    Ok(WindowCommand::WaitKeyPress) => {
-                        wait_for_key = true;
+                    sound_playing = playing;
+                }
+                // keep cycling the colors on static screens
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) if options.rainbow => {
+                    Self::draw(frame_buffer, &mut canvas, foreground, sound_indicator);
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(_err) => {
+                    eprintln!("Receiver died; quitting window");
+                    return;
+                }
+            };
+
+            for event in event_pump.poll_iter() {
+                match event {
+                    Event::Quit { .. } => return,
+                    Event::KeyDown {
+                        scancode: Some(Self::KEY_PASTE_ROM),
+                        repeat: false,
+                        ..
+                    } => match video_subsystem.clipboard().clipboard_text() {
+                        Ok(text) => {
+                            let _ = event_tx.send(HostEvent::PasteRom(text));
+                        }
+                        Err(err) => eprintln!("Failed to read clipboard: {err}"),
+                    },
+                    Event::KeyDown {
+                        scancode: Some(Self::KEY_SOFT_RESET),
+                        repeat: false,
+                        ..
+                    } => {
+                        let _ = event_tx.send(HostEvent::SoftReset);
                     }
-                    Ok(WindowCommand::ControlSound(true)) => audio.resume(),
-                    Ok(WindowCommand::ControlSound(false)) => audio.pause(),
-                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
-                    Err(_err) => {
-                        eprintln!("Receiver died; quitting window");
-                        return;
+                    Event::KeyDown {
+                        scancode: Some(scancode),
+                        ..
+                    } if Self::KEYS_SPEED_UP.contains(&scancode) => {
+                        let _ = event_tx.send(HostEvent::SpeedUp);
                     }
-                };
-
-                for event in event_pump.poll_iter() {
-                    match event {
-                        Event::Quit { .. } => return,
-                        Event::KeyUp {
-                            scancode: Some(key),
-                            ..
-                        } if wait_for_key => {
-                            if let Some(mapped_key) = Self::map_scancode(key) {
-                                respond_tx.send(mapped_key).expect("Failed to send keycode");
-                                wait_for_key = false;
-                            }
+                    Event::KeyDown {
+                        scancode: Some(scancode),
+                        ..
+                    } if Self::KEYS_SLOW_DOWN.contains(&scancode) => {
+                        let _ = event_tx.send(HostEvent::SlowDown);
+                    }
+                    event if wait_for_key => {
+                        if let Some(mapped_key) = Self::released_key(&event, keymap) {
+                            respond_tx.send(mapped_key).expect("Failed to send keycode");
+                            wait_for_key = false;
                         }
-                        _ => {}
                     }
+                    _ => {}
                 }
             }
-        }));
+        }
     }
 
-    /// Draws the screen based on the cucrrent [`Self::frame_buffer`].
-    fn draw(frame_buffer: &Arc<RwLock<[u64; Self::HEIGHT]>>, canvas: &mut WindowCanvas) {
+    /// Draws the screen based on the cucrrent [`Self::frame_buffer`], using the `foreground`
+    /// color for lit pixels.
+    ///
+    /// If `sound_indicator` is set, a dot is drawn in the top right corner.
+    fn draw(
+        frame_buffer: &Arc<RwLock<FrameBuffer>>,
+        canvas: &mut WindowCanvas,
+        foreground: Color,
+        sound_indicator: bool,
+    ) {
         let frame_buffer = frame_buffer.read().unwrap();
         // clear screen
         canvas.set_draw_color(Self::COLOR_BACKGROUND);
         canvas.clear();
 
         // draw new screen
-        canvas.set_draw_color(Self::COLOR_FOREGROUND);
-        for y in 0..Self::HEIGHT {
-            for x in 0..Self::WIDTH {
-                if (frame_buffer[y] & (1 << (Self::WIDTH - 1 - x))) == 0 {
+        canvas.set_draw_color(foreground);
+        // pixels of higher resolutions are drawn smaller, so the screen fits into the window
+        let scale = (Self::SCALE_FACTOR * Self::WIDTH / frame_buffer.width())
+            .min(Self::SCALE_FACTOR * Self::HEIGHT / frame_buffer.height());
+        for y in 0..frame_buffer.height() {
+            for x in 0..frame_buffer.width() {
+                if !frame_buffer.is_set(x, y) {
                     continue;
                 }
                 canvas
                     .fill_rect(Rect::new(
-                        (x * Self::SCALE_FACTOR) as i32,
-                        (y * Self::SCALE_FACTOR) as i32,
-                        Self::SCALE_FACTOR as u32,
-                        Self::SCALE_FACTOR as u32,
+                        (x * scale) as i32,
+                        (y * scale) as i32,
+                        scale as u32,
+                        scale as u32,
                     ))
                     .expect("Failed to draw rect");
             }
         }
+        if sound_indicator {
+            canvas.set_draw_color(Self::COLOR_SOUND_INDICATOR);
+            canvas
+                .fill_rect(Rect::new(
+                    ((Self::WIDTH - 1) * Self::SCALE_FACTOR) as i32,
+                    0,
+                    Self::SCALE_FACTOR as u32,
+                    Self::SCALE_FACTOR as u32,
+                ))
+                .expect("Failed to draw rect");
+        }
         canvas.present();
     }
 
-    /// Maps a scancode the an CHIP-8 key.
-    ///
-    /// The scancodes are mapped as follows:
-    /// Keypad       Keyboard
-    /// +-+-+-+-+    +-+-+-+-+
-    /// |1|2|3|C|    |1|2|3|4|
-    /// +-+-+-+-+    +-+-+-+-+
-    /// |4|5|6|D|    |Q|W|E|R|
-    /// +-+-+-+-+ => +-+-+-+-+
-    /// |7|8|9|E|    |A|S|D|F|
-    /// +-+-+-+-+    +-+-+-+-+
-    /// |A|0|B|F|    |Z|X|C|V|
-    /// +-+-+-+-+    +-+-+-+-+
-    fn map_scancode(key: Scancode) -> Option<u8> {
-        Some(match key {
-            Scancode::Num1 => 0x1,
-            Scancode::Num2 => 0x2,
-            Scancode::Num3 => 0x3,
-            Scancode::Num4 => 0xC,
-            Scancode::Q => 0x4,
-            Scancode::W => 0x5,
-            Scancode::E => 0x6,
-            Scancode::R => 0xD,
-            Scancode::A => 0x7,
-            Scancode::S => 0x8,
-            Scancode::D => 0x9,
-            Scancode::F => 0xE,
-            Scancode::Z => 0xA,
-            Scancode::X => 0x0,
-            Scancode::C => 0xB,
-            Scancode::V => 0xF,
-            _ => return None,
-        })
-    }
-
-    /// Maps a CHIP-8 key to a physical scancode.
+    /// Converts a `hue` in degrees (0 to 360) to a fully saturated and bright color.
+    fn hue_to_rgb(hue: f32) -> Color {
+        let sector = (hue / 60.0).rem_euclid(6.0);
+        // rising or falling component within the sector
+        let mid = ((1.0 - (sector % 2.0 - 1.0).abs()) * 255.0).round() as u8;
+        match sector as u8 {
+            0 => Color::RGB(255, mid, 0),
+            1 => Color::RGB(mid, 255, 0),
+            2 => Color::RGB(0, 255, mid),
+            3 => Color::RGB(0, mid, 255),
+            4 => Color::RGB(mid, 0, 255),
+            _ => Color::RGB(255, 0, mid),
+        }
+    }
+
+    /// Returns the CHIP-8 key released by the `event`, as mapped by the `keymap`, if any.
     ///
-    /// The keys are mapped as follows:
-    /// Keypad       Keyboard
-    /// +-+-+-+-+    +-+-+-+-+
-    /// |1|2|3|4|    |1|2|3|C|
-    /// +-+-+-+-+    +-+-+-+-+
-    /// |Q|W|E|R|    |4|5|6|D|
-    /// +-+-+-+-+ => +-+-+-+-+
-    /// |A|S|D|F|    |7|8|9|E|
-    /// +-+-+-+-+    +-+-+-+-+
-    /// |Z|X|C|V|    |A|0|B|F|
-    /// +-+-+-+-+    +-+-+-+-+
-    fn map_key(key: u8) -> Scancode {
-        match key {
-            0x1 => Scancode::Num1,
-            0x2 => Scancode::Num2,
-            0x3 => Scancode::Num3,
-            0xC => Scancode::Num4,
-            0x4 => Scancode::Q,
-            0x5 => Scancode::W,
-            0x6 => Scancode::E,
-            0xD => Scancode::R,
-            0x7 => Scancode::A,
-            0x8 => Scancode::S,
-            0x9 => Scancode::D,
-            0xE => Scancode::F,
-            0xA => Scancode::Z,
-            0x0 => Scancode::X,
-            0xB => Scancode::C,
-            0xF => Scancode::V,
-            _ => unreachable!("Trying to map invalid key {key}"),
+    /// Other events, e.g. resizing the window or pressing a key, do not end a wait for a key.
+    fn released_key(event: &Event, keymap: &Keymap) -> Option<u8> {
+        match event {
+            Event::KeyUp {
+                scancode: Some(key),
+                ..
+            } => keymap.key(*key),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "sdl")]
+    fn phase_increment_depends_on_sample_rate() {
+        assert_eq!(Beep::phase_increment(44100), 440.0 / 44100.0);
+        assert_eq!(
+            Beep::phase_increment(22050),
+            2.0 * Beep::phase_increment(44100)
+        );
+        // one period of the square wave takes 100 samples at 44 kHz
+        assert_eq!(Beep::phase_increment(44000) * 100.0, 1.0);
+    }
+
+    #[test]
+    #[cfg(feature = "sdl")]
+    fn only_key_release_ends_key_wait() {
+        use sdl2::{event::WindowEvent, keyboard::Mod};
+
+        let keymap = Keymap::default();
+        let resize = Event::Window {
+            timestamp: 0,
+            window_id: 1,
+            win_event: WindowEvent::Resized(100, 100),
+        };
+        assert_eq!(Window::released_key(&resize, &keymap), None);
+        let key_event = |scancode, up| {
+            let (timestamp, window_id, keycode, keymod, repeat) = (0, 1, None, Mod::NOMOD, false);
+            let scancode = Some(scancode);
+            if up {
+                Event::KeyUp {
+                    timestamp,
+                    window_id,
+                    keycode,
+                    scancode,
+                    keymod,
+                    repeat,
+                }
+            } else {
+                Event::KeyDown {
+                    timestamp,
+                    window_id,
+                    keycode,
+                    scancode,
+                    keymod,
+                    repeat,
+                }
+            }
+        };
+        assert_eq!(
+            Window::released_key(&key_event(Scancode::W, false), &keymap),
+            None
+        );
+        assert_eq!(
+            Window::released_key(&key_event(Scancode::W, true), &keymap),
+            Some(0x5)
+        );
+        assert_eq!(
+            Window::released_key(&key_event(Scancode::F5, true), &keymap),
+            None
+        );
+    }
+
+    #[test]
+    fn unchanged_screen_is_not_presented_again() {
+        let mut options = Window::new(Arc::default()).options;
+        let screen = (FrameBuffer::default(), false);
+        let mut changed = screen;
+        changed.0.xor_row(0, 1 << 127, 0, true);
+
+        assert!(Window::needs_present(options, Some(&screen), &screen));
+        options.skip_unchanged_frames = true;
+        assert!(!Window::needs_present(options, Some(&screen), &screen));
+        assert!(Window::needs_present(options, Some(&screen), &changed));
+        assert!(Window::needs_present(
+            options,
+            Some(&screen),
+            &(screen.0, true)
+        ));
+        assert!(Window::needs_present(options, None, &screen));
+    }
+
+    #[test]
+    #[cfg(feature = "sdl")]
+    fn keymap_can_be_extended() {
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            keymap.scancodes(0x5).collect::<Vec<_>>(),
+            [Scancode::W, Scancode::Up]
+        );
+        assert_eq!(keymap.key(Scancode::Up), Some(0x5));
+        assert_eq!(keymap.key(Scancode::Space), None);
+        // every key maps back to itself
+        for key in 0..16 {
+            assert!(keymap
+                .scancodes(key)
+                .all(|scancode| keymap.key(scancode) == Some(key)));
         }
+
+        keymap.bind(Scancode::Space, 0x5);
+        keymap.bind(Scancode::Up, 0x2);
+        assert_eq!(
+            keymap.scancodes(0x5).collect::<Vec<_>>(),
+            [Scancode::W, Scancode::Space]
+        );
+        assert_eq!(keymap.key(Scancode::Up), Some(0x2));
+    }
+
+    #[test]
+    #[cfg(feature = "sdl")]
+    fn hue_to_rgb_covers_color_wheel() {
+        assert_eq!(Window::hue_to_rgb(0.0), Color::RGB(255, 0, 0));
+        assert_eq!(Window::hue_to_rgb(60.0), Color::RGB(255, 255, 0));
+        assert_eq!(Window::hue_to_rgb(120.0), Color::RGB(0, 255, 0));
+        assert_eq!(Window::hue_to_rgb(180.0), Color::RGB(0, 255, 255));
+        assert_eq!(Window::hue_to_rgb(240.0), Color::RGB(0, 0, 255));
+        assert_eq!(Window::hue_to_rgb(300.0), Color::RGB(255, 0, 255));
+        // hues wrap around the color wheel
+        assert_eq!(Window::hue_to_rgb(360.0), Color::RGB(255, 0, 0));
+        assert_eq!(Window::hue_to_rgb(-120.0), Color::RGB(0, 0, 255));
+    }
+
+    #[test]
+    fn key_wait_does_not_block() {
+        let mut window = Window::new(Arc::default());
+        let (command_sender, commands) = std::sync::mpsc::channel();
+        let (key_sender, keys) = std::sync::mpsc::channel();
+        window.sender = Some(command_sender);
+        window.receiver = Some(keys);
+
+        assert_eq!(window.poll_key_release(), None);
+        assert_eq!(window.poll_key_release(), None);
+        // the window is only asked once for the released key
+        assert_eq!(
+            commands.try_iter().collect::<Vec<_>>(),
+            [WindowCommand::WaitKeyPress]
+        );
+        key_sender.send(0xB).unwrap();
+        assert_eq!(window.poll_key_release(), Some(0xB));
+        assert_eq!(window.poll_key_release(), None);
+        assert_eq!(
+            commands.try_iter().collect::<Vec<_>>(),
+            [WindowCommand::WaitKeyPress]
+        );
     }
 }